@@ -1,11 +1,64 @@
 fn main() {
+    // Baked into release builds for the attest command; see ATTESTATION_SECRET
+    println!("cargo:rerun-if-env-changed=LOCKDOWN_ATTESTATION_SECRET");
+    // Digests an external lockdown.config.json must match; see CONFIG_SHA256
+    println!("cargo:rerun-if-env-changed=LOCKDOWN_CONFIG_SHA256");
+    // Public key remote policies are verified with; see POLICY_PUBKEY
+    println!("cargo:rerun-if-env-changed=LOCKDOWN_POLICY_PUBKEY");
+
     const COMMANDS: &[&str] = &[
+        "submit_and_exit",
+        "attempt_exit",
+        "restart_clean",
+        "list_forbidden_processes",
+        "terminate_process",
+        "arm_hard_deadline",
+        "cancel_deadline",
+        "start_timer",
+        "stop_timer",
+        "show_proctor_banner",
+        "get_always_on_top",
+        "set_always_on_top",
         "close_app",
         "close_lockdown",
         "close_during_loading",
+        "confirm_emergency_exit",
+        "cancel_emergency_exit",
+        "reload_quiz",
+        "save_view_state",
+        "restore_view_state",
         "mark_quiz_ready",
+        "set_exam_active",
+        "check_for_update",
+        "apply_update",
+        "set_keyboard_lockdown",
+        "set_paste_allowed",
+        "set_accessibility",
+        "set_lockdown_stage",
+        "set_watermark_text",
+        "attest",
+        "get_script_version",
+        "get_config_hash",
+        "get_config",
+        "resolve_deeplink",
+        "export_violation_log",
+        "upload_violations",
+        "get_recent_violations",
+        "get_session_summary",
+        "clear_browser_data",
+        "get_foreground_window_title",
+        "capture_checkpoint",
         "check_multiple_monitors",
         "get_monitor_count",
+        "get_monitor_counts",
+        "set_fullscreen_on_monitor",
+        "get_power_status",
+        "get_displays",
+        "get_security_status",
+        "run_preflight",
+        "verify_self_integrity",
+        "rescan_environment",
+        "set_capture_exclusion",
     ];
 
     let attributes = tauri_build::Attributes::new()
@@ -0,0 +1,27 @@
+// The typed word is checked again in Rust; closing the window on Cancel or
+// Escape leaves the session running.
+(function () {
+  var form = document.getElementById('exit-form');
+  var word = document.getElementById('exit-word');
+  var error = document.getElementById('exit-error');
+
+  var cancel = function () {
+    window.__TAURI_INTERNALS__.invoke('cancel_emergency_exit');
+  };
+
+  form.addEventListener('submit', function (event) {
+    event.preventDefault();
+    window.__TAURI_INTERNALS__.invoke('confirm_emergency_exit', { word: word.value })
+      .catch(function (message) {
+        error.textContent = message;
+        word.select();
+      });
+  });
+  document.getElementById('exit-cancel').addEventListener('click', cancel);
+  document.addEventListener('keydown', function (event) {
+    if (event.key === 'Escape') {
+      cancel();
+    }
+  });
+  word.focus();
+})();
@@ -0,0 +1,46 @@
+// Retries reload_quiz with exponential backoff; the Retry button tries
+// immediately. Success navigates this window away, so there's nothing to do
+// on resolve.
+(function () {
+  var FIRST_DELAY_MS = 2000;
+  var MAX_DELAY_MS = 60000;
+
+  var button = document.getElementById('offline-retry');
+  var status = document.getElementById('offline-status');
+  var delay = FIRST_DELAY_MS;
+  var timer = null;
+  var countdown = null;
+
+  var schedule = function () {
+    var remaining = Math.round(delay / 1000);
+    status.textContent = 'Retrying in ' + remaining + 's.';
+    clearInterval(countdown);
+    countdown = setInterval(function () {
+      remaining -= 1;
+      if (remaining > 0) {
+        status.textContent = 'Retrying in ' + remaining + 's.';
+      }
+    }, 1000);
+    timer = setTimeout(retry, delay);
+    delay = Math.min(delay * 2, MAX_DELAY_MS);
+  };
+
+  var retry = function () {
+    clearTimeout(timer);
+    clearInterval(countdown);
+    button.disabled = true;
+    status.textContent = 'Connecting...';
+    window.__TAURI_INTERNALS__.invoke('reload_quiz').catch(function (error) {
+      console.warn('[Lockdown] quiz still unreachable', error);
+      button.disabled = false;
+      schedule();
+    });
+  };
+
+  button.addEventListener('click', function () {
+    delay = FIRST_DELAY_MS;
+    retry();
+  });
+
+  schedule();
+})();
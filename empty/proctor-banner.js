@@ -0,0 +1,11 @@
+// The message comes from show_proctor_banner, which sets the initial text
+// before this runs and calls the setter for later messages. The banner has
+// no way to close itself; Rust closes it when its time is up.
+(function () {
+  var text = document.getElementById('banner-text');
+
+  window.__UNDERSTANDLY_LOCKDOWN_SET_BANNER__ = function (message) {
+    text.textContent = String(message);
+  };
+  window.__UNDERSTANDLY_LOCKDOWN_SET_BANNER__(window.__UNDERSTANDLY_LOCKDOWN_BANNER_TEXT__ || '');
+})();
@@ -0,0 +1,105 @@
+    // These listeners are a backup to the native keyboard hook, which blocks
+    // shortcuts before they reach the webview; they only cover what the page
+    // itself receives. Page scripts still shouldn't be able to switch them
+    // off, so they are registered with the original addEventListener, on
+    // window in the capture phase (ahead of any page listener), with
+    // references the page never sees, and re-registered whenever the page
+    // might have torn them down.
+    (function () {
+        var addListener = EventTarget.prototype.addEventListener;
+        var apply = Function.prototype.call.bind(addListener);
+        var options = Object.freeze({ capture: true, passive: false });
+
+        // Paste is only honored inside input fields, and only while Rust has
+        // allowed it via set_paste_allowed (blocked by default)
+        var pasteAllowedIn = function (target) {
+            return window.__UNDERSTANDLY_LOCKDOWN_PASTE_ALLOWED__ === true &&
+                !!target && (target.tagName === 'INPUT' || target.tagName === 'TEXTAREA');
+        };
+
+        var handlers = Object.freeze({
+            // Disable right-click context menu
+            contextmenu: function (e) {
+                e.preventDefault();
+            },
+
+            // Block clipboard exfiltration
+            copy: function (e) { e.preventDefault(); },
+            cut: function (e) { e.preventDefault(); },
+            paste: function (e) {
+                if (pasteAllowedIn(e.target)) return;
+                e.preventDefault();
+            },
+
+            // Disable keyboard shortcuts (Ctrl on Windows/Linux, Cmd on macOS)
+            keydown: function (e) {
+                var mod = e.ctrlKey || e.metaKey;
+                var k = e.code;
+
+                // F12 (DevTools)
+                if (e.key === 'F12') {
+                    e.preventDefault();
+                    return;
+                }
+
+                // Ctrl/Cmd+Shift+I/J/C and Cmd+Option+I/J/C (DevTools, console, inspector)
+                if (mod && e.shiftKey && (k === 'KeyI' || k === 'KeyJ' || k === 'KeyC')) {
+                    e.preventDefault();
+                    return;
+                }
+                if (e.metaKey && e.altKey && (k === 'KeyI' || k === 'KeyJ' || k === 'KeyC')) {
+                    e.preventDefault();
+                    return;
+                }
+
+                if (mod && k === 'KeyV' && pasteAllowedIn(e.target)) {
+                    return;
+                }
+
+                // View source, save, print, copy/cut/paste, select-all
+                if (mod && ['KeyU', 'KeyS', 'KeyP', 'KeyC', 'KeyV', 'KeyX', 'KeyA'].indexOf(k) !== -1) {
+                    e.preventDefault();
+                    return;
+                }
+
+                // Cmd+W/M/H/Q/N/T (close, minimize, hide, quit, new window/tab)
+                if (e.metaKey && ['KeyW', 'KeyM', 'KeyH', 'KeyQ', 'KeyN', 'KeyT'].indexOf(k) !== -1) {
+                    e.preventDefault();
+                }
+            },
+
+            // Disable text selection (except in input fields)
+            selectstart: function (e) {
+                if (e.target.tagName === 'INPUT' || e.target.tagName === 'TEXTAREA') {
+                    return;
+                }
+                e.preventDefault();
+            },
+
+            // Disable drag and drop
+            dragstart: function (e) {
+                e.preventDefault();
+            }
+        });
+        // Captured now, so a page overriding Object.keys or Array methods
+        // can't break re-registration later
+        var types = Object.freeze(Object.keys(handlers));
+        for (var i = 0; i < types.length; i++) {
+            Object.freeze(handlers[types[i]]);
+        }
+
+        // Adding the same listener twice is a no-op, so this is safe to repeat
+        var register = function () {
+            for (var j = 0; j < types.length; j++) {
+                apply(window, types[j], handlers[types[j]], options);
+            }
+        };
+        register();
+
+        // document.open() drops every listener on the window, and swaps the
+        // document's children as it does
+        new MutationObserver(register).observe(document, { childList: true });
+        setInterval(register, 1000);
+    })();
+
+    console.log('[Lockdown] Security features initialized');
@@ -0,0 +1,42 @@
+// Writes each built executable's SHA-256 into its SELF_HASH_SLOT (see
+// verify_self_integrity in src/main.rs). Runs as Tauri's beforeBundleCommand,
+// after cargo has linked the binary and before the bundler signs and packages
+// it; pass paths to stamp other binaries.
+import { createHash } from 'node:crypto';
+import { existsSync, readFileSync, writeFileSync } from 'node:fs';
+import { join } from 'node:path';
+
+const TAG = Buffer.from('UL-SELF-SHA256::');
+const DIGEST_LEN = 32;
+
+const defaultPaths = () => {
+    const targetDir = process.env.CARGO_TARGET_DIR || 'target';
+    const profile = process.env.TAURI_ENV_DEBUG === 'true' ? 'debug' : 'release';
+    const triple = process.env.TAURI_ENV_TARGET_TRIPLE;
+    const dirs = [join(targetDir, profile)];
+    if (triple) dirs.unshift(join(targetDir, triple, profile));
+    return dirs
+        .flatMap((dir) => ['understandly_lockdown', 'understandly_lockdown.exe'].map((name) => join(dir, name)))
+        .filter(existsSync)
+        .slice(0, 1);
+};
+
+const paths = process.argv.length > 2 ? process.argv.slice(2) : defaultPaths();
+if (paths.length === 0) {
+    console.error('stamp-self-hash: no built executable found');
+    process.exit(1);
+}
+
+const empty = Buffer.concat([TAG, Buffer.alloc(DIGEST_LEN)]);
+for (const path of paths) {
+    const exe = readFileSync(path);
+    const at = exe.indexOf(empty);
+    if (at === -1 || exe.indexOf(empty, at + 1) !== -1) {
+        console.error(`stamp-self-hash: ${path} has no single unstamped slot`);
+        process.exit(1);
+    }
+    const digest = createHash('sha256').update(exe).digest();
+    digest.copy(exe, at + TAG.length);
+    writeFileSync(path, exe);
+    console.log(`stamp-self-hash: ${path} sha256 ${digest.toString('hex')}`);
+}
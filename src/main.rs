@@ -1,631 +1,7951 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use hmac::{Hmac, Mac};
+use minisign_verify::{PublicKey, Signature};
+use percent_encoding::percent_decode_str;
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tauri::{AppHandle, Manager, RunEvent, State, WebviewUrl, WebviewWindowBuilder};
+use sha2::{Digest, Sha256};
+#[cfg(target_os = "windows")]
+use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::mem;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+#[cfg(target_os = "windows")]
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::path::BaseDirectory;
+use tauri::webview::PageLoadEvent;
+use tauri::{AppHandle, Emitter, Manager, RunEvent, State, WebviewUrl, WebviewWindowBuilder};
 use tauri_plugin_deep_link::{DeepLinkExt, OpenUrlEvent};
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
-use tauri_plugin_updater::UpdaterExt;
+use tauri_plugin_updater::{Update, UpdaterExt};
 use url::Url;
 
 // ============================================================================
 // LockdownConfig - loaded from lockdown.config.json
 // ============================================================================
 
+/// How the main window fills the primary monitor
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum WindowMode {
+    /// The whole monitor, taskbar included
+    #[default]
+    Fullscreen,
+    /// Maximized without decorations: fills the work area, so the taskbar and
+    /// the touch keyboard stay usable on tablets
+    BorderlessMaximized,
+}
+
+/// `window.mode`, for restoring the main window once the loading sidebar
+/// closes
+static WINDOW_MODE: OnceLock<WindowMode> = OnceLock::new();
+
+/// Every field is optional; omitted ones take the defaults noted below.
 #[derive(Deserialize)]
+#[serde(default)]
 struct WindowConfig {
+    /// Default "Understandly Lockdown"
     title: String,
+    /// Default "fullscreen"
+    mode: WindowMode,
+    /// Only applies to the fullscreen mode. Default true
     fullscreen: bool,
+    /// Default true
     always_on_top: bool,
+    /// Seconds after a proctor drops always-on-top (`set_always_on_top`)
+    /// before it is put back automatically. Default 120
+    always_on_top_restore_secs: u64,
+    /// Default true
     skip_taskbar: bool,
+    /// Windows: re-assert topmost every this many milliseconds so toasts and
+    /// other topmost popups can't stay above the quiz. Default 0 (off)
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    reassert_topmost_ms: u64,
+    /// Cover every monitor but the primary with a black window, for setups
+    /// where a second display isn't needed (some use one for an
+    /// interpreter). Monitors connected later are not covered. Default false
+    cover_secondary_monitors: bool,
+    /// Seconds after startup during which closing the window exits cleanly,
+    /// so a bad launch can be abandoned. Ignored once the quiz is ready.
+    /// Default 0 (close is always prevented)
+    startup_grace_secs: u64,
+    /// Windows: stop the touch keyboard from opening by itself when a text
+    /// field is tapped, and put the quiz back on top if it opens anyway.
+    /// Default false: tablets without a keyboard need it
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    suppress_touch_keyboard: bool,
+    /// Keep files dragged in from other apps away from the page, so a notes
+    /// file can't be opened in the quiz window. Turn off only for a quiz
+    /// with a drag-and-drop upload area. Default true
+    block_file_drop: bool,
+    /// Title bar and borders. Default false
+    decorations: bool,
+    /// Default false
+    resizable: bool,
+    /// Default false
+    maximizable: bool,
+    /// E.g. for a student who needs to reach an assistive app. Default false
+    minimizable: bool,
+    /// Enables the close button; closing is still prevented outside
+    /// `startup_grace_secs`, as for Alt+F4. Default false
+    closable: bool,
+}
+
+impl WindowConfig {
+    /// The window options above that are switched on, i.e. less locked
+    /// down than the default
+    fn relaxed_options(&self) -> Vec<&'static str> {
+        [
+            ("decorations", self.decorations),
+            ("resizable", self.resizable),
+            ("maximizable", self.maximizable),
+            ("minimizable", self.minimizable),
+            ("closable", self.closable),
+            ("file drop", !self.block_file_drop),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+    }
 }
 
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "Understandly Lockdown".into(),
+            mode: WindowMode::Fullscreen,
+            fullscreen: true,
+            always_on_top: true,
+            always_on_top_restore_secs: 120,
+            skip_taskbar: true,
+            reassert_topmost_ms: 0,
+            cover_secondary_monitors: false,
+            startup_grace_secs: 0,
+            suppress_touch_keyboard: false,
+            block_file_drop: true,
+            decorations: false,
+            resizable: false,
+            maximizable: false,
+            minimizable: false,
+            closable: false,
+        }
+    }
+}
+
+/// Every field is optional; omitted ones take the defaults noted below.
 #[derive(Deserialize, Serialize)]
+#[serde(default)]
 struct LoadingRecoveryConfig {
+    /// Default true
     enabled: bool,
+    /// Default "Exit"
     button_label: String,
+    /// Default "The quiz is still loading. Close the lockdown browser?"; empty
+    /// skips the confirmation
     confirmation_message: String,
 }
 
-#[derive(Deserialize)]
-struct LockdownConfig {
-    base_url: String,
-    production_url: String,
-    window: WindowConfig,
-    loading_recovery: LoadingRecoveryConfig,
+impl Default for LoadingRecoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            button_label: "Exit".into(),
+            confirmation_message: "The quiz is still loading. Close the lockdown browser?".into(),
+        }
+    }
 }
 
-impl LockdownConfig {
-    fn load() -> Self {
-        let config_str = include_str!("../lockdown.config.json");
-        serde_json::from_str(config_str).expect("Invalid lockdown.config.json")
-    }
+/// Modifier keys a blocked combination requires to be held.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+enum KeyModifier {
+    Ctrl,
+    Alt,
+    Shift,
+    Win,
 }
 
-// ============================================================================
-// Initialization script - injected into every page load (survives navigation)
-// ============================================================================
+/// What the keyboard hook does with a combination that matches
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+enum KeyAction {
+    /// Suppress the key and emit `blocked-key`
+    #[default]
+    Block,
+    /// Let the key through but emit `warned-key`
+    Warn,
+    /// Let the key through even if a `block` or `warn` entry (or the Windows
+    /// key chord rule) also matches
+    Allow,
+}
 
-const INIT_SCRIPT: &str = r#"
-    // Disable right-click context menu
-    document.addEventListener('contextmenu', function (e) {
-        e.preventDefault();
-    });
+/// A key plus the modifiers that must be held for it to match. Extra
+/// modifiers don't prevent a match: `Alt+Tab` also blocks `Ctrl+Alt+Tab`.
+#[derive(Deserialize)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct KeyCombo {
+    #[serde(default)]
+    modifiers: Vec<KeyModifier>,
+    key: String,
+    /// Default block
+    #[serde(default)]
+    action: KeyAction,
+}
 
-    // Block clipboard exfiltration
-    document.addEventListener('copy', function (e) { e.preventDefault(); });
-    document.addEventListener('cut', function (e) { e.preventDefault(); });
-    document.addEventListener('paste', function (e) { e.preventDefault(); });
+impl KeyCombo {
+    fn new(modifiers: &[KeyModifier], key: &str) -> Self {
+        Self {
+            modifiers: modifiers.to_vec(),
+            key: key.into(),
+            action: KeyAction::Block,
+        }
+    }
+}
 
-    // Disable keyboard shortcuts (Ctrl on Windows/Linux, Cmd on macOS)
-    document.addEventListener('keydown', function (e) {
-        var mod = e.ctrlKey || e.metaKey;
-        var k = e.code;
+#[derive(Deserialize)]
+#[serde(default)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct KeyboardConfig {
+    blocked_combos: Vec<KeyCombo>,
+    /// Keys always let through when pressed without Ctrl, Alt or Windows,
+    /// whatever `blocked_combos` says, e.g. a screen reader's F1-F8
+    allowed_keys: Vec<String>,
+    /// Seconds between checks that the hook still suppresses keys; 0
+    /// disables. Default 5
+    hook_watchdog_secs: u64,
+    /// Switch off the Sticky Keys (Shift five times), Filter Keys (hold right
+    /// Shift) and Toggle Keys (hold Num Lock) shortcuts for the session, whose
+    /// dialogs can open above the quiz. The features themselves stay as the
+    /// student set them. Default true
+    block_accessibility_shortcuts: bool,
+}
 
-        // F12 (DevTools)
-        if (e.key === 'F12') {
-            e.preventDefault();
-            return;
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        use KeyModifier::{Alt, Ctrl, Shift, Win};
+        Self {
+            blocked_combos: vec![
+                KeyCombo::new(&[Alt], "Tab"),
+                KeyCombo::new(&[Alt], "Escape"),
+                KeyCombo::new(&[Alt], "F4"),
+                // Task Manager. Requires both modifiers, so plain Escape and
+                // Ctrl+Escape (Start menu) keep their usual handling.
+                KeyCombo::new(&[Ctrl, Shift], "Escape"),
+                KeyCombo::new(&[], "LWin"),
+                KeyCombo::new(&[], "RWin"),
+                KeyCombo::new(&[], "PrintScreen"),
+                // Snipping Tool overlay. Also covered by the Win chord rule,
+                // but listed explicitly so it stays blocked if LWin/RWin are
+                // removed from the list.
+                KeyCombo::new(&[Win, Shift], "S"),
+                KeyCombo::new(&[], "F12"),
+                KeyCombo::new(&[Ctrl], "C"),
+                KeyCombo::new(&[Ctrl], "V"),
+                KeyCombo::new(&[Ctrl], "P"),
+            ],
+            allowed_keys: Vec::new(),
+            hook_watchdog_secs: 5,
+            block_accessibility_shortcuts: true,
         }
+    }
+}
 
-        // Ctrl/Cmd+Shift+I/J/C and Cmd+Option+I/J/C (DevTools, console, inspector)
-        if (mod && e.shiftKey && (k === 'KeyI' || k === 'KeyJ' || k === 'KeyC')) {
-            e.preventDefault();
-            return;
-        }
-        if (e.metaKey && e.altKey && (k === 'KeyI' || k === 'KeyJ' || k === 'KeyC')) {
-            e.preventDefault();
-            return;
-        }
+/// Native recovery shortcut, e.g. `{ "modifiers": ["ctrl", "alt", "shift"],
+/// "key": "Q" }`. Names follow the global-shortcut plugin: modifiers are
+/// ctrl/control, alt/option, shift, super/cmd; keys are letters, digits, F1-F24
+/// or codes such as `KeyQ`.
+#[derive(Deserialize)]
+#[serde(default)]
+struct EmergencyExitConfig {
+    modifiers: Vec<String>,
+    key: String,
+    /// Shortcut to register instead when the one above is taken by another
+    /// app. Default Ctrl+Alt+Shift+X
+    fallback: Option<ShortcutConfig>,
+}
 
-        // View source, save, print, copy/cut/paste, select-all
-        if (mod && ['KeyU', 'KeyS', 'KeyP', 'KeyC', 'KeyV', 'KeyX', 'KeyA'].indexOf(k) !== -1) {
-            e.preventDefault();
-            return;
+impl Default for EmergencyExitConfig {
+    fn default() -> Self {
+        Self {
+            modifiers: vec!["Ctrl".into(), "Alt".into(), "Shift".into()],
+            key: "Q".into(),
+            fallback: Some(ShortcutConfig {
+                modifiers: vec!["Ctrl".into(), "Alt".into(), "Shift".into()],
+                key: "X".into(),
+            }),
         }
+    }
+}
 
-        // Cmd+W/M/H/Q/N/T (close, minimize, hide, quit, new window/tab)
-        if (e.metaKey && ['KeyW', 'KeyM', 'KeyH', 'KeyQ', 'KeyN', 'KeyT'].indexOf(k) !== -1) {
-            e.preventDefault();
-        }
-    });
+#[derive(Deserialize)]
+struct ShortcutConfig {
+    #[serde(default)]
+    modifiers: Vec<String>,
+    key: String,
+}
 
-    // Disable text selection (except in input fields)
-    document.addEventListener('selectstart', function (e) {
-        if (e.target.tagName === 'INPUT' || e.target.tagName === 'TEXTAREA') {
-            return;
+impl ShortcutConfig {
+    /// The shortcut and its display label, or `None` (logged) if it doesn't
+    /// parse
+    fn parse(modifiers: &[String], key: &str) -> Option<(Shortcut, String)> {
+        let label = modifiers
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(key))
+            .collect::<Vec<_>>()
+            .join("+");
+        match label.parse::<Shortcut>() {
+            Ok(shortcut) => Some((shortcut, label)),
+            Err(error) => {
+                log::warn!("invalid emergency_exit shortcut {label:?}: {error}");
+                None
+            }
         }
-        e.preventDefault();
-    });
-
-    // Disable drag and drop
-    document.addEventListener('dragstart', function (e) {
-        e.preventDefault();
-    });
+    }
+}
 
-    console.log('[Lockdown] Security features initialized');
-"#;
+impl EmergencyExitConfig {
+    /// Shortcuts to try registering, in order: the configured one, or
+    /// Ctrl+Alt+Shift+Q if it doesn't parse, then the fallback.
+    fn candidates(&self) -> Vec<(Shortcut, String)> {
+        let primary = ShortcutConfig::parse(&self.modifiers, &self.key).unwrap_or_else(|| {
+            let shortcut = Shortcut::new(
+                Some(Modifiers::CONTROL | Modifiers::ALT | Modifiers::SHIFT),
+                Code::KeyQ,
+            );
+            (shortcut, "Ctrl+Alt+Shift+Q".into())
+        });
+        let fallback = self
+            .fallback
+            .as_ref()
+            .and_then(|fallback| ShortcutConfig::parse(&fallback.modifiers, &fallback.key))
+            .filter(|(shortcut, _)| *shortcut != primary.0);
+        std::iter::once(primary).chain(fallback).collect()
+    }
+}
 
-fn loading_recovery_script(config: &LoadingRecoveryConfig) -> String {
-    let config_json = serde_json::to_string(config)
-        .expect("loading recovery configuration should serialize to JSON");
-    format!(
-        r#"
-        window.__UNDERSTANDLY_LOADING_RECOVERY_CONFIG__ = {config_json};
-        document.addEventListener('DOMContentLoaded', function () {{
-            var config = window.__UNDERSTANDLY_LOADING_RECOVERY_CONFIG__;
-            var button = document.getElementById('loading-exit');
-            var cancelButton = document.getElementById('loading-cancel');
-            var confirmButton = document.getElementById('loading-confirm');
-            var confirmation = document.getElementById('loading-confirmation');
-            var confirmationMessage = document.getElementById('loading-confirmation-message');
-            var status = document.getElementById('loading-status');
-            if (!button) return;
-            var buttonLabel = config.button_label || 'Exit setup';
+/// Every field is optional; omitted ones take the defaults noted below.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct NetworkAllowlistConfig {
+    /// Hold everything the main window loads, not just where it navigates,
+    /// to `allowed_origins`. Default false
+    enabled: bool,
+    /// WebSocket origins the page may connect to while `enabled` is on, e.g.
+    /// `wss://proctor.example.com`. Default none
+    websocket_origins: Vec<String>,
+}
 
-            var updateButtonState = function (disabled) {{
-                button.textContent = buttonLabel;
-                button.setAttribute('aria-label', buttonLabel);
-                button.disabled = disabled;
-            }};
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct SecurityConfig {
+    /// Proctor exit password for `attempt_exit`, as
+    /// `pbkdf2-sha256$<iterations>$<salt>$<hex digest>`. Default none (the
+    /// command is disabled). Never serialized, so `get_config` can't leak it
+    #[serde(skip_serializing)]
+    exit_password_hash: Option<String>,
+    /// Make the main window come out blank in screenshots and recordings
+    /// (SetWindowDisplayAffinity). Default false: this also blanks it in a
+    /// proctor's legitimate screen share
+    exclude_from_capture: bool,
+    /// Refuse to start inside a virtual machine (see `virtual_machine_artifact`
+    /// for the heuristics). Default false
+    block_virtual_machines: bool,
+    /// Executable names (case-insensitive) that keep the app from starting,
+    /// typically screen recorders and remote-control tools
+    blocked_processes: Vec<String>,
+    /// Seconds between process scans while the exam runs
+    process_scan_interval_secs: u64,
+    /// Consecutive scans a blocklisted process must survive before
+    /// `forbidden-process` fires, so short-lived processes are ignored
+    process_grace_scans: u32,
+    /// Empty the clipboard whenever the lockdown window gains focus, so text
+    /// copied elsewhere can't be pasted in (and exam text can't leave)
+    clear_clipboard_on_focus: bool,
+    /// Also empty the clipboard every this many seconds; 0 disables
+    clear_clipboard_interval_secs: u64,
+    /// Ignore `--safe-mode`, so exam machines can't be started without the
+    /// native lockdown. Default true in release builds, false in debug builds
+    disallow_safe_mode: bool,
+    /// Give every launch its own webview profile and wipe it on exit, so
+    /// cookies don't carry over to the next student. Default true
+    isolate_browser_data: bool,
+    /// HTTPS origins `upload_violations` may send the violation log to, e.g.
+    /// `https://proctor.example.com`. Default empty (uploads are disabled)
+    violation_upload_origins: Vec<String>,
+}
 
-            var hideConfirmation = function () {{
-                if (!confirmation) return;
-                confirmation.dataset.open = 'false';
-                confirmation.setAttribute('aria-hidden', 'true');
-            }};
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            exit_password_hash: None,
+            exclude_from_capture: false,
+            block_virtual_machines: false,
+            blocked_processes: [
+                "obs64.exe",
+                "obs32.exe",
+                "anydesk.exe",
+                "teamviewer.exe",
+                "rustdesk.exe",
+                "parsecd.exe",
+                "bdcam.exe",
+                "sharex.exe",
+            ]
+            .map(String::from)
+            .to_vec(),
+            process_scan_interval_secs: 3,
+            process_grace_scans: 2,
+            clear_clipboard_on_focus: true,
+            clear_clipboard_interval_secs: 0,
+            disallow_safe_mode: !cfg!(debug_assertions),
+            isolate_browser_data: true,
+            violation_upload_origins: Vec::new(),
+        }
+    }
+}
 
-            var showConfirmation = function () {{
-                if (!confirmation) return false;
+impl SecurityConfig {
+    /// Whether `name` is on the blocklist (case-insensitive)
+    #[cfg(target_os = "windows")]
+    fn is_blocked(&self, name: &str) -> bool {
+        self.blocked_processes
+            .iter()
+            .any(|blocked| blocked.eq_ignore_ascii_case(name))
+    }
+}
 
-                if (confirmationMessage) {{
-                    confirmationMessage.textContent = config.confirmation_message;
-                }}
+/// Companion process that relaunches the app if it is killed; see
+/// `run_watchdog`
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct WatchdogConfig {
+    /// Honor the `--watchdog` launch argument
+    enabled: bool,
+}
 
-                confirmation.dataset.open = 'true';
-                confirmation.setAttribute('aria-hidden', 'false');
-                if (cancelButton) {{
-                    cancelButton.focus();
-                }}
-                return true;
-            }};
+/// Repeating text across the quiz, to deter photographing the screen
+#[derive(Deserialize)]
+#[serde(default)]
+struct WatermarkConfig {
+    enabled: bool,
+    /// Text repeated across the page; `{text}` is replaced with what
+    /// `set_watermark_text` provides, e.g. the student's name. Default "{text}"
+    template: String,
+    /// Between 0 and 1. Default 0.08
+    opacity: f32,
+}
 
-            updateButtonState(false);
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            template: "{text}".into(),
+            opacity: 0.08,
+        }
+    }
+}
 
-            button.addEventListener('click', function () {{
-                if (config.confirmation_message) {{
-                    if (!showConfirmation()) return;
-                    return;
-                }}
+/// Flash the quiz red, and optionally beep, when a violation at least this
+/// severe is emitted, so an invigilator in the room notices at once
+#[derive(Deserialize)]
+#[serde(default)]
+struct AlarmConfig {
+    enabled: bool,
+    /// Default "critical"
+    min_severity: Severity,
+    /// Also beep through the page's audio. Default false
+    sound: bool,
+    /// How long each alarm lasts before clearing itself. Default 5
+    duration_secs: u32,
+}
 
-                updateButtonState(true);
-                window.__TAURI_INTERNALS__.invoke('close_during_loading').catch(function (error) {{
-                    updateButtonState(false);
-                    console.error('[Lockdown] loading exit rejected', error);
-                }});
-            }});
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_severity: Severity::Critical,
+            sound: false,
+            duration_secs: 5,
+        }
+    }
+}
 
-            if (cancelButton) {{
-                cancelButton.addEventListener('click', hideConfirmation);
-            }}
+/// What `set_lockdown_stage` applies for one exam phase
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+struct LockdownStage {
+    /// Windows: the keyboard hook, as `set_keyboard_lockdown`. Default true
+    keyboard_lockdown: bool,
+    /// As `set_paste_allowed`. Default false
+    paste_allowed: bool,
+    /// Default true
+    always_on_top: bool,
+    /// Emit `monitor-changed` when a display is connected or removed. Default
+    /// true
+    monitor_enforcement: bool,
+}
 
-            if (confirmButton) {{
-                confirmButton.addEventListener('click', function () {{
-                    hideConfirmation();
-                    updateButtonState(true);
-                    window.__TAURI_INTERNALS__.invoke('close_during_loading').catch(function (error) {{
-                        updateButtonState(false);
-                        console.error('[Lockdown] loading exit rejected', error);
-                    }});
-                }});
-            }}
+impl Default for LockdownStage {
+    fn default() -> Self {
+        Self {
+            keyboard_lockdown: true,
+            paste_allowed: false,
+            always_on_top: true,
+            monitor_enforcement: true,
+        }
+    }
+}
 
-            window.__UNDERSTANDLY_LOCKDOWN_UPDATING__ = function () {{
-                button.textContent = 'Updating...';
-                button.disabled = true;
-                if (status) {{
-                    status.textContent = 'An update is being installed before the quiz opens.';
-                }}
-                hideConfirmation();
-            }};
+impl LockdownStage {
+    /// Whether this stage turns off any protection the default stage has,
+    /// so switching to it needs the exit password
+    fn relaxes_protection(&self) -> bool {
+        !self.keyboard_lockdown
+            || self.paste_allowed
+            || !self.always_on_top
+            || !self.monitor_enforcement
+    }
+}
 
-            window.__UNDERSTANDLY_LOCKDOWN_RESTORE_LOADING__ = function () {{
-                button.textContent = config.button_label || 'Exit setup';
-                button.disabled = false;
-                if (status) {{
-                    status.textContent = 'The quiz has not started yet.';
-                }}
-            }};
-        }}, {{ once: true }});
-        "#
-    )
+/// Resuming a session after a crash, see `load_recovery_marker`
+#[derive(Deserialize)]
+#[serde(default)]
+struct CrashRecoveryConfig {
+    /// Reopen the page a crashed session was on if it crashed less than this
+    /// many minutes ago; 0 disables. Default 30
+    max_age_minutes: u64,
 }
 
-enum QuizPhase {
-    CheckingUpdate { ready_requested: bool },
-    Loading,
-    Ready,
-    Updating { ready_requested: bool },
+impl Default for CrashRecoveryConfig {
+    fn default() -> Self {
+        Self {
+            max_age_minutes: 30,
+        }
+    }
 }
 
-struct QuizSessionState {
-    phase: Mutex<QuizPhase>,
+/// Display adjustments for students who need them (e.g. under an IEP),
+/// applied to the hosted page by `accessibility_script`
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+struct AccessibilityConfig {
+    /// Sets `data-lockdown-high-contrast="true"` on `<html>` for the page's
+    /// own high-contrast styles. Default false
+    high_contrast: bool,
+    /// Root font size multiplier. Default 1.0
+    font_scale: f32,
 }
 
-impl QuizSessionState {
-    fn new(check_updates: bool) -> Self {
-        let phase = if check_updates {
-            QuizPhase::CheckingUpdate {
-                ready_requested: false,
-            }
-        } else {
-            QuizPhase::Loading
-        };
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
         Self {
-            phase: Mutex::new(phase),
+            high_contrast: false,
+            font_scale: 1.0,
         }
     }
+}
 
-    fn is_ready(&self) -> bool {
-        matches!(*self.phase.lock().unwrap(), QuizPhase::Ready)
+/// Diagnostics for field technicians
+#[derive(Deserialize)]
+#[serde(default)]
+struct DebugSettings {
+    /// Least severe level written to lockdown.log: "error", "warn", "info",
+    /// "debug", "trace" or "off". Default "info"
+    log_level: String,
+    /// Make the emergency exit shortcut ask for `EXIT_CONFIRMATION_WORD`
+    /// first, unless `INSTANT_EXIT_ENV` is set. Default false
+    exit_confirmation: bool,
+}
+
+impl Default for DebugSettings {
+    fn default() -> Self {
+        Self {
+            log_level: "info".into(),
+            exit_confirmation: false,
+        }
     }
 }
 
-// ============================================================================
-// Windows Security Module - Low-level keyboard hook
-// ============================================================================
+/// Inactivity thresholds, in minutes; 0 disables either one
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct IdleConfig {
+    /// Minutes without input before `idle-warning`
+    warning_minutes: u64,
+    /// Minutes without input before `idle-timeout`
+    timeout_minutes: u64,
+    /// While idle detection is on, look for mouse input from a jiggler app
+    /// and emit `synthetic-input-suspected`. Default true
+    detect_synthetic_input: bool,
+}
 
-#[cfg(target_os = "windows")]
-mod windows_security {
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::thread;
-    use windows::core::PCWSTR;
-    use windows::Win32::Foundation::{HINSTANCE, LPARAM, LRESULT, WPARAM};
-    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
-    use windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
-    use windows::Win32::UI::WindowsAndMessaging::{
-        CallNextHookEx, DispatchMessageW, GetMessageW, SetWindowsHookExW, TranslateMessage,
-        UnhookWindowsHookEx, HHOOK, KBDLLHOOKSTRUCT, MSG, WH_KEYBOARD_LL, WM_KEYDOWN,
-        WM_SYSKEYDOWN,
-    };
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            warning_minutes: 0,
+            timeout_minutes: 0,
+            detect_synthetic_input: true,
+        }
+    }
+}
 
-    static HOOK_ACTIVE: AtomicBool = AtomicBool::new(false);
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct PowerConfig {
+    /// Battery percentage below which `low-battery` fires while unplugged; 0
+    /// disables. Default 20
+    low_battery_percent: u8,
+}
 
-    // Virtual key codes
-    const VK_TAB: u32 = 0x09;
-    const VK_ESCAPE: u32 = 0x1B;
-    const VK_LWIN: u32 = 0x5B;
-    const VK_RWIN: u32 = 0x5C;
-    const VK_SNAPSHOT: u32 = 0x2C; // PrintScreen
-    const VK_F4: u32 = 0x73;
-    const VK_F12: u32 = 0x7B;
-    const VK_C: u32 = 0x43;
-    const VK_V: u32 = 0x56;
-    const VK_P: u32 = 0x50;
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            low_battery_percent: 20,
+        }
+    }
+}
 
-    // Modifier key flags from KBDLLHOOKSTRUCT
-    const LLKHF_ALTDOWN: u32 = 0x20;
+/// Language of the text the app itself shows (error dialogs, the loading
+/// sidebar's status lines)
+#[derive(Deserialize)]
+#[serde(default)]
+struct I18nConfig {
+    /// BCP 47 tag such as "fr" or "fr-CA"; "fr-CA" falls back to "fr", then
+    /// English. Default "en"
+    locale: String,
+    /// Per-locale overrides of the bundled strings, keyed by message key
+    messages: HashMap<String, HashMap<String, String>>,
+}
 
-    /// Low-level keyboard hook callback
-    /// Blocks: Alt+Tab, Alt+Esc, Alt+F4, Windows key, PrintScreen, Ctrl+C/V/P, F12
-    unsafe extern "system" fn keyboard_hook_proc(
-        code: i32,
-        wparam: WPARAM,
-        lparam: LPARAM,
-    ) -> LRESULT {
-        if code >= 0 {
-            let kb_struct = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
-            let vk_code = kb_struct.vkCode;
-            let flags = kb_struct.flags.0;
-            let alt_down = (flags & LLKHF_ALTDOWN) != 0;
+impl Default for I18nConfig {
+    fn default() -> Self {
+        Self {
+            locale: "en".into(),
+            messages: HashMap::new(),
+        }
+    }
+}
 
-            // Check for Ctrl key via GetAsyncKeyState
-            let ctrl_down = (GetAsyncKeyState(0x11) as u16 & 0x8000) != 0;
+/// What happens when the exam window loses focus
+#[derive(Deserialize)]
+#[serde(default)]
+struct FocusConfig {
+    /// Focus losses after which `lockdown-violation` is emitted; 0 never fires
+    max_focus_losses: u32,
+    /// Take focus back immediately after each loss
+    refocus_on_blur: bool,
+    /// Cover the page with a blurred overlay while the window is unfocused
+    hide_content_on_blur: bool,
+}
 
-            let is_key_down = wparam.0 as u32 == WM_KEYDOWN || wparam.0 as u32 == WM_SYSKEYDOWN;
+impl Default for FocusConfig {
+    fn default() -> Self {
+        Self {
+            max_focus_losses: 3,
+            refocus_on_blur: false,
+            hide_content_on_blur: false,
+        }
+    }
+}
 
-            if is_key_down {
-                // Block Alt+Tab, Alt+Escape, Alt+F4
-                if alt_down && (vk_code == VK_TAB || vk_code == VK_ESCAPE || vk_code == VK_F4) {
-                    return LRESULT(1);
+/// Which of the two configured URLs to load
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Environment {
+    /// `base_url`, what debug builds load
+    Base,
+    /// `production_url`, what release builds load
+    Production,
+}
+
+impl Environment {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "base" => Some(Self::Base),
+            "production" => Some(Self::Production),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LockdownConfig {
+    /// Required: the URL debug builds load
+    base_url: String,
+    /// Required: the URL release builds load
+    production_url: String,
+    /// Load this URL regardless of the build type, e.g. to point a release
+    /// build at staging. `--environment` overrides it
+    #[serde(default)]
+    force_environment: Option<Environment>,
+    /// Signed policy fetched at startup that extends or replaces the blocked
+    /// keys, blocked processes and allowed origins; see `RemotePolicy`
+    #[serde(default)]
+    policy_url: Option<String>,
+    #[serde(default)]
+    window: WindowConfig,
+    #[serde(default)]
+    loading_recovery: LoadingRecoveryConfig,
+    #[serde(default)]
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    keyboard: KeyboardConfig,
+    #[serde(default)]
+    emergency_exit: EmergencyExitConfig,
+    #[serde(default)]
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    security: SecurityConfig,
+    /// Origins the main window may navigate to, e.g. `https://www.example.com`.
+    /// Empty means only the origin of the active base URL.
+    #[serde(default)]
+    allowed_origins: Vec<String>,
+    #[serde(default)]
+    network_allowlist: NetworkAllowlistConfig,
+    /// Deep-link paths that may be opened, e.g. `quiz` or `results/*` (a
+    /// trailing `*` matches any path with that prefix). Empty allows every
+    /// path.
+    #[serde(default)]
+    allowed_deeplink_paths: Vec<String>,
+    /// URL schemes accepted as deep links, for co-branded builds. Empty means
+    /// only `DEEP_LINK_SCHEME`.
+    #[serde(default)]
+    deep_link_schemes: Vec<String>,
+    #[serde(default)]
+    focus: FocusConfig,
+    #[serde(default)]
+    watchdog: WatchdogConfig,
+    #[serde(default)]
+    crash_recovery: CrashRecoveryConfig,
+    #[serde(default)]
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    idle: IdleConfig,
+    #[serde(default)]
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    power: PowerConfig,
+    #[serde(default)]
+    accessibility: AccessibilityConfig,
+    #[serde(default)]
+    watermark: WatermarkConfig,
+    #[serde(default)]
+    alarm: AlarmConfig,
+    /// Named presets for `set_lockdown_stage`, e.g. `instructions`, `exam`
+    /// and `review`
+    #[serde(default)]
+    stages: BTreeMap<String, LockdownStage>,
+    #[serde(default)]
+    debug_settings: DebugSettings,
+    #[serde(default)]
+    i18n: I18nConfig,
+}
+
+/// Name of the config file, both embedded and as a deploy-time override
+const CONFIG_FILE_NAME: &str = "lockdown.config.json";
+
+/// Comma-separated SHA-256 hex digests an external config must match, injected
+/// at compile time from `LOCKDOWN_CONFIG_SHA256`. Unset or empty accepts any
+/// external config.
+const CONFIG_SHA256: Option<&str> = option_env!("LOCKDOWN_CONFIG_SHA256");
+
+/// Where the effective config came from and its SHA-256, for audit
+#[derive(Clone, Serialize)]
+struct ConfigInfo {
+    source: String,
+    sha256: String,
+    /// Whether it came from a file rather than the embedded copy
+    #[serde(skip)]
+    external: bool,
+}
+
+static CONFIG_INFO: OnceLock<ConfigInfo> = OnceLock::new();
+
+impl LockdownConfig {
+    /// Load the `--config` file if given, else `lockdown.config.json` from
+    /// next to the executable if present, so IT can swap configs per
+    /// environment without rebuilding, otherwise the copy embedded at build
+    /// time. Errors name the file and the failing field, line and column.
+    fn load(path: Option<&Path>) -> Result<Self, String> {
+        let (source, config_str) = Self::read(path)?;
+        let config =
+            serde_json::from_str(&config_str).map_err(|error| format!("{source}: {error}"))?;
+        let sha256 = hex(&Sha256::digest(config_str.as_bytes()));
+        log::info!("using config {source} (sha256 {sha256})");
+        let external = source != Self::embedded().0;
+        let _ = CONFIG_INFO.set(ConfigInfo {
+            source,
+            sha256,
+            external,
+        });
+        Ok(config)
+    }
+
+    /// Just the `i18n` section, for reporting a config that fails to load in
+    /// the language it asks for. English if even that can't be read.
+    fn load_i18n(path: Option<&Path>) -> I18nConfig {
+        Self::read(path)
+            .ok()
+            .and_then(|(_, config_str)| {
+                let mut config: serde_json::Value = serde_json::from_str(&config_str).ok()?;
+                serde_json::from_value(config.get_mut("i18n")?.take()).ok()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Where the config comes from, for messages, and its contents. An
+    /// external config that doesn't match `CONFIG_SHA256` is refused in favor
+    /// of the embedded copy, so editing it can't loosen the lockdown.
+    fn read(path: Option<&Path>) -> Result<(String, String), String> {
+        let external = path.map(Path::to_path_buf).or_else(|| {
+            std::env::current_exe()
+                .ok()
+                .and_then(|exe| Some(exe.parent()?.join(CONFIG_FILE_NAME)))
+                .filter(|path| path.is_file())
+        });
+
+        match external {
+            Some(path) => {
+                let source = path.display().to_string();
+                let config_str =
+                    fs::read_to_string(&path).map_err(|error| format!("{source}: {error}"))?;
+                if external_config_trusted(&config_str) {
+                    return Ok((source, config_str));
                 }
+                log::error!(
+                    "{source} doesn't match LOCKDOWN_CONFIG_SHA256; using the embedded config"
+                );
+                Ok(Self::embedded())
+            }
+            None => Ok(Self::embedded()),
+        }
+    }
 
-                // Block Windows key (left and right)
-                if vk_code == VK_LWIN || vk_code == VK_RWIN {
-                    return LRESULT(1);
+    fn embedded() -> (String, String) {
+        (
+            format!("embedded {CONFIG_FILE_NAME}"),
+            include_str!("../lockdown.config.json").into(),
+        )
+    }
+}
+
+/// Whether the loaded config is one IT vouched for: the embedded copy, or an
+/// external file checked against a non-empty `CONFIG_SHA256`
+fn config_vetted() -> bool {
+    let pinned = CONFIG_SHA256
+        .unwrap_or_default()
+        .split(',')
+        .any(|digest| !digest.trim().is_empty());
+    pinned || CONFIG_INFO.get().is_some_and(|info| !info.external)
+}
+
+/// Whether `config_str` may be used, per `CONFIG_SHA256`
+fn external_config_trusted(config_str: &str) -> bool {
+    let mut allowed = CONFIG_SHA256
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|digest| !digest.is_empty())
+        .peekable();
+    if allowed.peek().is_none() {
+        return true;
+    }
+    let digest = hex(&Sha256::digest(config_str.as_bytes()));
+    allowed.any(|expected| expected.eq_ignore_ascii_case(&digest))
+}
+
+/// Source and SHA-256 of the config this session runs with
+#[tauri::command]
+fn get_config_hash() -> Result<ConfigInfo, String> {
+    CONFIG_INFO
+        .get()
+        .cloned()
+        .ok_or_else(|| "config info is unavailable".into())
+}
+
+/// The config as `get_config` shows it to the page: branding and which
+/// protections are on. Build-time secrets (attestation secret, policy key)
+/// aren't config at all, and `SecurityConfig` skips the exit password hash.
+#[derive(Clone, Serialize)]
+struct PublicConfig {
+    title: String,
+    /// The environment actually loaded, after `--environment` and
+    /// `force_environment`
+    environment: Environment,
+    base_url: String,
+    window_mode: WindowMode,
+    safe_mode: bool,
+    /// Whether `attempt_exit` is enabled
+    exit_password_set: bool,
+    security: SecurityConfig,
+    network_allowlist_enabled: bool,
+    watermark_enabled: bool,
+    max_focus_losses: u32,
+    idle_timeout_minutes: u64,
+}
+
+impl PublicConfig {
+    fn new(
+        config: &LockdownConfig,
+        environment: Environment,
+        base_url: &str,
+        safe_mode: bool,
+    ) -> Self {
+        Self {
+            title: config.window.title.clone(),
+            environment,
+            base_url: base_url.to_owned(),
+            window_mode: config.window.mode,
+            safe_mode,
+            exit_password_set: config.security.exit_password_hash.is_some(),
+            security: config.security.clone(),
+            network_allowlist_enabled: config.network_allowlist.enabled,
+            watermark_enabled: config.watermark.enabled,
+            max_focus_losses: config.focus.max_focus_losses,
+            idle_timeout_minutes: config.idle.timeout_minutes,
+        }
+    }
+}
+
+/// The parsed config, redacted, so the page doesn't have to duplicate it
+#[tauri::command]
+fn get_config(config: State<'_, PublicConfig>) -> PublicConfig {
+    config.inner().clone()
+}
+
+// ============================================================================
+// Command-line Arguments
+// ============================================================================
+
+/// Options IT can pass for troubleshooting. Anything else on the command line
+/// (deep-link URLs the OS appends, arguments for the watchdog's child) is
+/// ignored here.
+#[derive(Default)]
+struct CliArgs {
+    /// `--config <path>` or `--config=<path>`: load this file instead of the
+    /// one next to the executable or the embedded copy
+    config: Option<PathBuf>,
+    /// `--debug`: log at debug level, whatever `debug_settings.log_level` says
+    debug: bool,
+    /// `--environment <base|production>`: overrides `force_environment`
+    environment: Option<Environment>,
+    /// `--safe-mode`: start without any native hooks or enforcement, see
+    /// `security.disallow_safe_mode` and `config_vetted`
+    safe_mode: bool,
+    /// `--watchdog`: run as the watchdog, see `WATCHDOG_ARG`
+    watchdog: bool,
+}
+
+impl CliArgs {
+    fn parse(args: impl IntoIterator<Item = OsString>) -> Self {
+        let mut cli = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--config" {
+                match args.next() {
+                    Some(path) => cli.config = Some(path.into()),
+                    None => log::warn!("--config needs a path; ignoring it"),
                 }
+            } else if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+                cli.config = Some(path.into());
+            } else if arg == "--environment" {
+                match args.next().as_ref().and_then(|name| name.to_str()) {
+                    Some(name) => cli.set_environment(name),
+                    None => log::warn!("--environment needs base or production; ignoring it"),
+                }
+            } else if let Some(name) = arg
+                .to_str()
+                .and_then(|arg| arg.strip_prefix("--environment="))
+            {
+                cli.set_environment(name);
+            } else if arg == "--debug" {
+                cli.debug = true;
+            } else if arg == "--safe-mode" {
+                cli.safe_mode = true;
+            } else if arg == WATCHDOG_ARG {
+                cli.watchdog = true;
+            }
+        }
+        cli
+    }
+
+    fn set_environment(&mut self, name: &str) {
+        match Environment::parse(name) {
+            Some(environment) => self.environment = Some(environment),
+            None => log::warn!("unknown --environment {name:?}; expected base or production"),
+        }
+    }
+}
+
+// ============================================================================
+// Localization - user-facing strings, see I18nConfig
+// ============================================================================
+
+/// Bundled message tables. English must have every key; other locales fall
+/// back to it key by key.
+const MESSAGES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "en",
+        &[
+            (
+                "config-invalid",
+                "The lockdown browser's configuration is invalid:\n\n{error}",
+            ),
+            (
+                "remote-session",
+                "The lockdown browser can't run inside a Remote Desktop session.",
+            ),
+            (
+                "virtual-machine",
+                "The lockdown browser can't run inside a virtual machine. \
+                 Open the quiz on your computer directly.",
+            ),
+            (
+                "blocked-processes",
+                "The lockdown browser can't start while these programs are running:\n\n\
+                 {programs}\n\nClose them and open the quiz again.",
+            ),
+            ("loading-title", "Preparing the quiz environment"),
+            (
+                "loading-description",
+                "The browser is loading the secure test. \
+                 You can close this screen if you need to exit the setup.",
+            ),
+            ("loading-updating", "Updating..."),
+            (
+                "loading-updating-status",
+                "An update is being installed before the quiz opens.",
+            ),
+            ("loading-status", "The quiz has not started yet."),
+            ("loading-confirm-title", "Confirm exit"),
+            ("loading-confirm", "Close browser"),
+            ("loading-cancel", "Cancel"),
+            ("safe-mode", "SAFE MODE - lockdown disabled"),
+            (
+                "startup-failed",
+                "The lockdown browser could not start:\n\n{error}",
+            ),
+            (
+                "error-reference",
+                "Reference: {reference}. Give it to your IT support.",
+            ),
+        ],
+    ),
+    (
+        "fr",
+        &[
+            (
+                "config-invalid",
+                "La configuration du navigateur sécurisé n'est pas valide :\n\n{error}",
+            ),
+            (
+                "remote-session",
+                "Le navigateur sécurisé ne peut pas fonctionner dans une session Bureau à distance.",
+            ),
+            (
+                "virtual-machine",
+                "Le navigateur sécurisé ne peut pas fonctionner dans une machine virtuelle. \
+                 Ouvrez le questionnaire directement sur votre ordinateur.",
+            ),
+            (
+                "blocked-processes",
+                "Le navigateur sécurisé ne peut pas démarrer pendant que ces programmes sont \
+                 ouverts :\n\n{programs}\n\nFermez-les et ouvrez le questionnaire de nouveau.",
+            ),
+            ("loading-title", "Préparation de l'environnement du questionnaire"),
+            (
+                "loading-description",
+                "Le navigateur charge le test sécurisé. \
+                 Vous pouvez fermer cet écran si vous devez quitter la configuration.",
+            ),
+            ("loading-updating", "Mise à jour..."),
+            (
+                "loading-updating-status",
+                "Une mise à jour est en cours d'installation avant l'ouverture du questionnaire.",
+            ),
+            ("loading-status", "Le questionnaire n'a pas encore commencé."),
+            ("loading-confirm-title", "Confirmer la sortie"),
+            ("loading-confirm", "Fermer le navigateur"),
+            ("loading-cancel", "Annuler"),
+            ("safe-mode", "MODE SANS ÉCHEC - verrouillage désactivé"),
+            (
+                "startup-failed",
+                "Le navigateur sécurisé n'a pas pu démarrer :\n\n{error}",
+            ),
+            (
+                "error-reference",
+                "Référence : {reference}. Communiquez-la à votre support informatique.",
+            ),
+        ],
+    ),
+];
+
+static I18N: OnceLock<I18nConfig> = OnceLock::new();
+
+/// The message for `key` in the configured locale: config overrides first,
+/// then the bundled table, for the full tag, its language, and finally
+/// English. `{name}` placeholders are replaced from `args`.
+fn message(key: &str, args: &[(&str, &str)]) -> String {
+    let i18n = I18N.get_or_init(I18nConfig::default);
+    let language = i18n.locale.split(['-', '_']).next().unwrap_or_default();
+
+    let mut text = [i18n.locale.as_str(), language, "en"]
+        .into_iter()
+        .find_map(|locale| {
+            let configured = i18n
+                .messages
+                .get(locale)
+                .and_then(|messages| messages.get(key))
+                .cloned();
+            configured.or_else(|| {
+                MESSAGES
+                    .iter()
+                    .find(|(bundled, _)| bundled.eq_ignore_ascii_case(locale))
+                    .and_then(|(_, messages)| messages.iter().find(|(k, _)| *k == key))
+                    .map(|(_, text)| (*text).into())
+            })
+        })
+        .unwrap_or_else(|| key.into());
+
+    for (name, value) in args {
+        text = text.replace(&format!("{{{name}}}"), value);
+    }
+    text
+}
+
+// ============================================================================
+// Logging - lockdown.log in the app data dir
+// ============================================================================
+
+/// `identifier` in tauri.conf.json. The log is opened before Tauri starts, so
+/// the app data dir (`<data dir>/<identifier>`) is built from this by hand.
+const APP_IDENTIFIER: &str = "com.understandly.lockdown";
+
+const LOG_FILE_NAME: &str = "lockdown.log";
+
+/// Size at which lockdown.log is moved to lockdown.log.1 on startup
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Writes every record to lockdown.log, so windowed release builds still
+/// leave a log to attach to a ticket, and echoes it to stderr for
+/// `cargo tauri dev`.
+struct FileLogger {
+    file: Option<Mutex<File>>,
+}
+
+impl log::Log for FileLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+        eprintln!("[Lockdown] {} {}", record.level(), record.args());
+        if let Some(file) = &self.file {
+            let _ = writeln!(
+                file.lock().unwrap(),
+                "{timestamp_ms} {:<5} {}",
+                record.level(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+static LOGGER: OnceLock<FileLogger> = OnceLock::new();
+
+/// Open lockdown.log and install the logger at info level. Runs first in
+/// `main`, so config errors are logged too; `debug_settings.log_level`
+/// adjusts the level once the config has loaded.
+fn init_logging() {
+    let file = dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .and_then(|dir| {
+            fs::create_dir_all(&dir).ok()?;
+            let path = dir.join(LOG_FILE_NAME);
+            if fs::metadata(&path).is_ok_and(|meta| meta.len() > MAX_LOG_BYTES) {
+                let _ = fs::rename(&path, path.with_extension("log.1"));
+            }
+            OpenOptions::new().create(true).append(true).open(path).ok()
+        });
+    let logger = LOGGER.get_or_init(|| FileLogger {
+        file: file.map(Mutex::new),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+    }
+}
+
+// ============================================================================
+// Initialization script - injected into every page load (survives navigation)
+// ============================================================================
+
+/// scripts/init.js as of this build, used when the bundled resource can't be
+/// read
+const INIT_SCRIPT: &str = include_str!("../scripts/init.js");
+
+/// Bump whenever scripts/init.js changes
+const INIT_SCRIPT_VERSION: &str = "2";
+
+/// Path of the init script inside the bundle's resource directory
+const INIT_SCRIPT_RESOURCE: &str = "scripts/init.js";
+
+#[derive(Clone, Serialize)]
+struct InitScriptInfo {
+    /// INIT_SCRIPT_VERSION, or "custom" when the bundled file differs from
+    /// the script this build embeds
+    version: &'static str,
+    /// Lowercase hex SHA-256 of the script actually injected, so a server can
+    /// reject a modified copy
+    sha256: String,
+    /// "resource" or "embedded"
+    source: &'static str,
+}
+
+/// The script the main window injects, set once during setup
+static INIT_SCRIPT_INFO: OnceLock<InitScriptInfo> = OnceLock::new();
+
+/// Read the bundled init script, falling back to the embedded copy
+fn load_init_script(app: &AppHandle) -> (String, InitScriptInfo) {
+    let bundled = app
+        .path()
+        .resolve(INIT_SCRIPT_RESOURCE, BaseDirectory::Resource)
+        .map_err(|error| error.to_string())
+        .and_then(|path| fs::read_to_string(&path).map_err(|error| error.to_string()));
+    let (script, source) = match bundled {
+        Ok(script) => (script, "resource"),
+        Err(error) => {
+            log::warn!("using embedded init script: {error}");
+            (INIT_SCRIPT.to_owned(), "embedded")
+        }
+    };
+
+    let info = InitScriptInfo {
+        version: if script == INIT_SCRIPT {
+            INIT_SCRIPT_VERSION
+        } else {
+            "custom"
+        },
+        sha256: hex(&Sha256::digest(script.as_bytes())),
+        source,
+    };
+    (script, info)
+}
+
+/// Which init script (and so which page-level protections) is active
+#[tauri::command]
+fn get_script_version() -> Result<InitScriptInfo, String> {
+    INIT_SCRIPT_INFO
+        .get()
+        .cloned()
+        .ok_or_else(|| "the init script has not been loaded".into())
+}
+
+/// Font scales `set_accessibility` and the config accept
+const FONT_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+
+/// Applies the accessibility settings to every page load. A live change from
+/// `set_accessibility` is kept in sessionStorage so it survives navigation
+/// within the quiz. Only presentation is touched; text selection and the other
+/// blocked interactions stay governed by INIT_SCRIPT.
+fn accessibility_script(config: &AccessibilityConfig) -> String {
+    let config_json = serde_json::to_string(config)
+        .expect("accessibility configuration should serialize to JSON");
+    format!(
+        r#"
+        (function () {{
+            var storageKey = '__understandly_lockdown_accessibility__';
+            var apply = function (settings) {{
+                var root = document.documentElement;
+                if (!root) return;
+                root.setAttribute('data-lockdown-high-contrast', settings.high_contrast ? 'true' : 'false');
+                root.style.setProperty('--lockdown-font-scale', String(settings.font_scale));
+                root.style.fontSize = (settings.font_scale * 100) + '%';
+            }};
+
+            var settings = {config_json};
+            try {{
+                var stored = sessionStorage.getItem(storageKey);
+                if (stored) settings = JSON.parse(stored);
+            }} catch (e) {{}}
+
+            window.__UNDERSTANDLY_LOCKDOWN_SET_ACCESSIBILITY__ = function (next) {{
+                settings = next;
+                try {{
+                    sessionStorage.setItem(storageKey, JSON.stringify(next));
+                }} catch (e) {{}}
+                apply(settings);
+            }};
+
+            apply(settings);
+            document.addEventListener('DOMContentLoaded', function () {{
+                apply(settings);
+            }});
+        }})();
+        "#
+    )
+}
+
+/// Id of the overlay `watermark_script` adds to every page
+const WATERMARK_ID: &str = "__understandly_lockdown_watermark__";
+
+/// Defines `__UNDERSTANDLY_LOCKDOWN_SET_WATERMARK__` on every page, which
+/// covers it with a click-through overlay repeating the given text. A
+/// MutationObserver puts the overlay back if the page removes or restyles
+/// it; the function itself can't be replaced. The text comes from
+/// `set_watermark_text` and is passed again after every page load.
+fn watermark_script(opacity: f32) -> String {
+    let opacity = opacity.clamp(0.0, 1.0);
+    format!(
+        r#"
+        (function () {{
+            var text = '';
+            var expected = '';
+            var escape = function (value) {{
+                return value.replace(/[&<>"']/g, function (c) {{
+                    return '&#' + c.charCodeAt(0) + ';';
+                }});
+            }};
+            var ensure = function () {{
+                if (!text || !document.documentElement) return;
+                var overlay = document.getElementById('{WATERMARK_ID}');
+                if (!overlay) {{
+                    overlay = document.createElement('div');
+                    overlay.id = '{WATERMARK_ID}';
+                }}
+                var svg = '<svg xmlns="http://www.w3.org/2000/svg" width="360" height="220">' +
+                    '<text x="180" y="110" text-anchor="middle" transform="rotate(-30 180 110)" ' +
+                    'font-family="sans-serif" font-size="18" fill="black">' + escape(text) +
+                    '</text></svg>';
+                overlay.style.cssText = 'position:fixed;inset:0;z-index:2147483647;' +
+                    'pointer-events:none;opacity:{opacity};background-repeat:repeat;' +
+                    'background-image:url("data:image/svg+xml,' + encodeURIComponent(svg) + '")';
+                expected = overlay.style.cssText;
+                // Last child, so it stacks above other fixed max-z-index layers
+                if (overlay !== document.documentElement.lastElementChild) {{
+                    document.documentElement.appendChild(overlay);
+                }}
+            }};
+
+            Object.defineProperty(window, '__UNDERSTANDLY_LOCKDOWN_SET_WATERMARK__', {{
+                value: function (next) {{
+                    text = String(next);
+                    ensure();
+                }}
+            }});
+
+            new MutationObserver(function () {{
+                if (!text) return;
+                var overlay = document.getElementById('{WATERMARK_ID}');
+                if (!overlay || overlay.style.cssText !== expected || overlay.hidden) {{
+                    if (overlay) overlay.hidden = false;
+                    ensure();
+                }}
+            }}).observe(document, {{
+                childList: true,
+                subtree: true,
+                attributes: true,
+                attributeFilter: ['style', 'class', 'hidden', 'id']
+            }});
+        }})();
+        "#
+    )
+}
+
+/// Banner across the top of every page while `--safe-mode` is on, so nobody
+/// mistakes the session for a locked-down exam
+fn safe_mode_script() -> String {
+    let label_json = serde_json::to_string(&message("safe-mode", &[]))
+        .expect("safe mode label should serialize to JSON");
+    format!(
+        r#"
+        document.addEventListener('DOMContentLoaded', function () {{
+            var banner = document.createElement('div');
+            banner.textContent = {label_json};
+            banner.setAttribute('role', 'status');
+            banner.style.cssText = 'position:fixed;top:0;left:0;right:0;z-index:2147483647;' +
+                'padding:4px;background:#b00020;color:#fff;font:bold 14px sans-serif;' +
+                'text-align:center;pointer-events:none;opacity:0.9';
+            document.documentElement.appendChild(banner);
+        }}, {{ once: true }});
+        "#
+    )
+}
+
+fn loading_recovery_script(config: &LoadingRecoveryConfig) -> String {
+    let config_json = serde_json::to_string(config)
+        .expect("loading recovery configuration should serialize to JSON");
+    let messages_json = serde_json::json!({
+        "title": message("loading-title", &[]),
+        "description": message("loading-description", &[]),
+        "updating": message("loading-updating", &[]),
+        "updating_status": message("loading-updating-status", &[]),
+        "status": message("loading-status", &[]),
+        "confirm_title": message("loading-confirm-title", &[]),
+        "confirm": message("loading-confirm", &[]),
+        "cancel": message("loading-cancel", &[]),
+    });
+    format!(
+        r#"
+        window.__UNDERSTANDLY_LOADING_RECOVERY_CONFIG__ = {config_json};
+        document.addEventListener('DOMContentLoaded', function () {{
+            var config = window.__UNDERSTANDLY_LOADING_RECOVERY_CONFIG__;
+            var messages = {messages_json};
+            var button = document.getElementById('loading-exit');
+            var cancelButton = document.getElementById('loading-cancel');
+            var confirmButton = document.getElementById('loading-confirm');
+            var confirmation = document.getElementById('loading-confirmation');
+            var confirmationMessage = document.getElementById('loading-confirmation-message');
+            var status = document.getElementById('loading-status');
+            if (!button) return;
+            var title = document.getElementById('loading-title');
+            var description = document.getElementById('loading-description');
+            if (title) title.textContent = messages.title;
+            if (description) description.textContent = messages.description;
+            if (status) status.textContent = messages.status;
+            var confirmTitle = document.querySelector('.confirm-title');
+            if (confirmTitle) confirmTitle.textContent = messages.confirm_title;
+            if (confirmButton) confirmButton.textContent = messages.confirm;
+            if (cancelButton) cancelButton.textContent = messages.cancel;
+            var buttonLabel = config.button_label || 'Exit setup';
+
+            var updateButtonState = function (disabled) {{
+                button.textContent = buttonLabel;
+                button.setAttribute('aria-label', buttonLabel);
+                button.disabled = disabled;
+            }};
+
+            var hideConfirmation = function () {{
+                if (!confirmation) return;
+                confirmation.dataset.open = 'false';
+                confirmation.setAttribute('aria-hidden', 'true');
+            }};
+
+            var showConfirmation = function () {{
+                if (!confirmation) return false;
+
+                if (confirmationMessage) {{
+                    confirmationMessage.textContent = config.confirmation_message;
+                }}
+
+                confirmation.dataset.open = 'true';
+                confirmation.setAttribute('aria-hidden', 'false');
+                if (cancelButton) {{
+                    cancelButton.focus();
+                }}
+                return true;
+            }};
+
+            updateButtonState(false);
+
+            button.addEventListener('click', function () {{
+                if (config.confirmation_message) {{
+                    if (!showConfirmation()) return;
+                    return;
+                }}
+
+                updateButtonState(true);
+                window.__TAURI_INTERNALS__.invoke('close_during_loading').catch(function (error) {{
+                    updateButtonState(false);
+                    console.error('[Lockdown] loading exit rejected', error);
+                }});
+            }});
+
+            if (cancelButton) {{
+                cancelButton.addEventListener('click', hideConfirmation);
+            }}
+
+            if (confirmButton) {{
+                confirmButton.addEventListener('click', function () {{
+                    hideConfirmation();
+                    updateButtonState(true);
+                    window.__TAURI_INTERNALS__.invoke('close_during_loading').catch(function (error) {{
+                        updateButtonState(false);
+                        console.error('[Lockdown] loading exit rejected', error);
+                    }});
+                }});
+            }}
+
+            window.__UNDERSTANDLY_LOCKDOWN_UPDATING__ = function () {{
+                button.textContent = messages.updating;
+                button.disabled = true;
+                if (status) {{
+                    status.textContent = messages.updating_status;
+                }}
+                hideConfirmation();
+            }};
+
+            window.__UNDERSTANDLY_LOCKDOWN_RESTORE_LOADING__ = function () {{
+                button.textContent = config.button_label || 'Exit setup';
+                button.disabled = false;
+                if (status) {{
+                    status.textContent = messages.status;
+                }}
+            }};
+        }}, {{ once: true }});
+        "#
+    )
+}
+
+enum QuizPhase {
+    CheckingUpdate { ready_requested: bool },
+    Loading,
+    Ready,
+    Updating { ready_requested: bool },
+}
+
+struct QuizSessionState {
+    phase: Mutex<QuizPhase>,
+    /// Set by `mark_quiz_ready` and `set_exam_active`; `apply_update` refuses
+    /// to restart the app while it is true
+    exam_active: AtomicBool,
+}
+
+impl QuizSessionState {
+    fn new(check_updates: bool) -> Self {
+        let phase = if check_updates {
+            QuizPhase::CheckingUpdate {
+                ready_requested: false,
+            }
+        } else {
+            QuizPhase::Loading
+        };
+        Self {
+            phase: Mutex::new(phase),
+            exam_active: AtomicBool::new(false),
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        matches!(*self.phase.lock().unwrap(), QuizPhase::Ready)
+    }
+}
+
+// ============================================================================
+// Windows Security Module - Low-level keyboard hook
+// ============================================================================
+
+#[cfg(target_os = "windows")]
+mod windows_security {
+    use super::{
+        DisplayBounds, DisplayInfo, InputDevices, KeyAction, KeyCombo, KeyModifier, KeyboardConfig,
+        PowerStatus,
+    };
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::{Mutex, OnceLock};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use webview2_com::Microsoft::Web::WebView2::Win32::COREWEBVIEW2_WEB_RESOURCE_CONTEXT_ALL;
+    use webview2_com::{take_pwstr, WebResourceRequestedEventHandler};
+    use windows::core::{BOOL, HSTRING, PCWSTR, PWSTR};
+    use windows::Win32::Foundation::{
+        CloseHandle, HANDLE, HINSTANCE, HWND, LPARAM, LRESULT, RECT, TRUE, TRUST_E_NOSIGNATURE,
+        WPARAM,
+    };
+    use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+    use windows::Win32::Graphics::Gdi::{
+        CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, EnumDisplayMonitors,
+        GetDC, GetDIBits, GetMonitorInfoW, ReleaseDC, SelectObject, BITMAPINFO, BITMAPINFOHEADER,
+        BI_RGB, DIB_RGB_COLORS, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW,
+    };
+    use windows::Win32::Security::WinTrust::{
+        WinVerifyTrust, WINTRUST_ACTION_GENERIC_VERIFY_V2, WINTRUST_DATA, WINTRUST_DATA_0,
+        WINTRUST_FILE_INFO, WTD_CHOICE_FILE, WTD_REVOKE_NONE, WTD_STATEACTION_CLOSE,
+        WTD_STATEACTION_VERIFY, WTD_UI_NONE,
+    };
+    use windows::Win32::Storage::Xps::{PrintWindow, PRINT_WINDOW_FLAGS, PW_CLIENTONLY};
+    use windows::Win32::System::DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard};
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    use windows::Win32::System::Registry::{
+        RegCloseKey, RegDeleteKeyValueW, RegGetValueW, RegOpenKeyExW, RegSetKeyValueW, HKEY,
+        HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_READ, REG_DWORD, RRF_RT_REG_DWORD,
+    };
+    use windows::Win32::System::SystemInformation::GetTickCount;
+    use windows::Win32::System::Threading::{
+        GetCurrentThreadId, OpenProcess, QueryFullProcessImageNameW, TerminateProcess,
+        PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE,
+    };
+    use windows::Win32::UI::Accessibility::{
+        FILTERKEYS, FKF_HOTKEYACTIVE, SKF_HOTKEYACTIVE, STICKYKEYS, TKF_HOTKEYACTIVE, TOGGLEKEYS,
+    };
+    use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        GetAsyncKeyState, GetKeyboardLayout, GetLastInputInfo, SendInput, ToUnicodeEx, INPUT,
+        INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP, LASTINPUTINFO,
+        VIRTUAL_KEY,
+    };
+    use windows::Win32::UI::Input::{
+        GetRawInputDeviceInfoW, GetRawInputDeviceList, RAWINPUTDEVICELIST, RIDI_DEVICENAME,
+        RIM_TYPEKEYBOARD, RIM_TYPEMOUSE,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CallNextHookEx, DispatchMessageW, FindWindowW, GetClientRect, GetForegroundWindow,
+        GetMessageW, GetSystemMetrics, GetWindowLongW, GetWindowTextW, GetWindowThreadProcessId,
+        IsWindowVisible, MessageBoxW, PeekMessageW, PostThreadMessageW, SetWindowDisplayAffinity,
+        SetWindowPos, SetWindowsHookExW, SystemParametersInfoW, TranslateMessage,
+        UnhookWindowsHookEx, GWL_STYLE, HWND_TOPMOST, KBDLLHOOKSTRUCT, MB_ICONERROR, MB_OK,
+        MB_SETFOREGROUND, MB_TOPMOST, MONITORINFOF_PRIMARY, MSG, MSLLHOOKSTRUCT, PM_NOREMOVE,
+        SM_REMOTESESSION, SPI_GETFILTERKEYS, SPI_GETSTICKYKEYS, SPI_GETTOGGLEKEYS,
+        SPI_SETFILTERKEYS, SPI_SETSTICKYKEYS, SPI_SETTOGGLEKEYS, SWP_NOACTIVATE, SWP_NOMOVE,
+        SWP_NOSIZE, SYSTEM_PARAMETERS_INFO_ACTION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+        WDA_EXCLUDEFROMCAPTURE, WDA_MONITOR, WDA_NONE, WH_KEYBOARD_LL, WH_MOUSE_LL, WM_KEYDOWN,
+        WM_MOUSEMOVE, WM_QUIT, WM_SYSKEYDOWN, WS_DISABLED,
+    };
+
+    static HOOK_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    /// Id of the thread running the hook's message loop, or 0 when none is
+    /// running. `uninstall_keyboard_hook` posts WM_QUIT to it.
+    static HOOK_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+
+    /// How long `uninstall_keyboard_hook` waits for the hook thread to unhook
+    const UNINSTALL_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// How long `install_keyboard_hook` waits for SetWindowsHookExW to return
+    const INSTALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Installs slower than this are logged as a warning, since keys pressed
+    /// in the meantime aren't blocked
+    const SLOW_INSTALL: Duration = Duration::from_millis(100);
+
+    /// Milliseconds the last successful install took from the call to the
+    /// hook being confirmed, or u64::MAX if no install has succeeded
+    static HOOK_INSTALL_MS: AtomicU64 = AtomicU64::new(u64::MAX);
+
+    /// Set by `set_paste_allowed` while the page has an editable field
+    /// focused. Starts false so a frontend crash leaves paste blocked.
+    static PASTE_ALLOWED: AtomicBool = AtomicBool::new(false);
+
+    /// Combinations from `keyboard.blocked_combos`, resolved to virtual key
+    /// codes once so the hook callback never touches the config.
+    static BLOCKED_COMBOS: OnceLock<Vec<BlockedCombo>> = OnceLock::new();
+
+    /// Virtual key codes from `keyboard.allowed_keys`, resolved with
+    /// `BLOCKED_COMBOS`
+    static ALLOWED_KEYS: OnceLock<Vec<u32>> = OnceLock::new();
+
+    // Virtual key codes
+    const VK_SHIFT: i32 = 0x10;
+    const VK_CONTROL: i32 = 0x11;
+    const VK_LSHIFT: u32 = 0xA0;
+    const VK_RSHIFT: u32 = 0xA1;
+    const VK_MENU: i32 = 0x12;
+    const VK_LCONTROL: i32 = 0xA2;
+    const VK_RCONTROL: i32 = 0xA3;
+    const VK_RMENU: i32 = 0xA5;
+    const VK_LWIN: i32 = 0x5B;
+    const VK_RWIN: i32 = 0x5C;
+    const VK_V: u32 = 0x56;
+    const VK_F24: u16 = 0x87;
+
+    // Flags from KBDLLHOOKSTRUCT
+    const LLKHF_EXTENDED: u32 = 0x01;
+    const LLKHF_ALTDOWN: u32 = 0x20;
+
+    // Scan codes of the Windows keys; both arrive with the extended (E0) bit
+    const SCAN_LWIN: u32 = 0x5B;
+    const SCAN_RWIN: u32 = 0x5C;
+
+    /// `dwExtraInfo` of the keystrokes `run_self_test` injects, so the hook can
+    /// tell them from a real F24 press
+    const SELF_TEST_TAG: usize = 0x4C4B_5445;
+
+    /// How long `run_self_test` waits for the hook to see its keystroke
+    const SELF_TEST_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Set by the hook when it swallows the self-test keystroke
+    static SELF_TEST_SEEN: AtomicBool = AtomicBool::new(false);
+
+    /// Outcome of the last `run_self_test`: 0 not run, 1 passed, 2 failed
+    static SELF_TEST_RESULT: AtomicU8 = AtomicU8::new(0);
+
+    /// Whether a Windows key is currently down, tracked from the hook's own
+    /// events. GetAsyncKeyState can't be relied on here: a Win press the hook
+    /// swallows never reaches the async key state, so Win+Tab would not look
+    /// like a Win chord.
+    static WIN_DOWN: AtomicBool = AtomicBool::new(false);
+
+    /// Event time (GetTickCount ms) of the last key press the hook suppressed,
+    /// including the self-test's own keystrokes
+    static LAST_BLOCKED_TICK: AtomicU32 = AtomicU32::new(0);
+
+    /// Presses Windows counts towards the Sticky Keys shortcut
+    const STICKY_KEYS_PRESSES: u32 = 5;
+
+    /// Longest gap between two Shift presses that still continues a run, in
+    /// milliseconds
+    const SHIFT_RUN_GAP_MS: u32 = 1500;
+
+    /// `keyboard.block_accessibility_shortcuts`
+    static GUARD_STICKY_KEYS: AtomicBool = AtomicBool::new(false);
+
+    /// Shift presses in a row with no other key in between, see `swallow_shift`
+    static SHIFT_RUN: AtomicU32 = AtomicU32::new(0);
+
+    /// Event time of the last Shift press
+    static LAST_SHIFT_TICK: AtomicU32 = AtomicU32::new(0);
+
+    /// Whether Shift is down, to tell auto-repeat from a new press
+    static SHIFT_HELD: AtomicBool = AtomicBool::new(false);
+
+    /// Whether the current Shift press is being swallowed, so its repeats and
+    /// release are too
+    static SHIFT_SWALLOWED: AtomicBool = AtomicBool::new(false);
+
+    /// Whether to swallow this event as part of a run of Shift presses that
+    /// would open the Sticky Keys dialog. Every press from the fifth on is
+    /// swallowed until another key is pressed or the run pauses, so Windows
+    /// never counts past four. Backs up `disable_accessibility_shortcuts`,
+    /// which group policy can undo.
+    fn swallow_shift(kb: &KBDLLHOOKSTRUCT, is_key_down: bool) -> bool {
+        if kb.vkCode != VK_LSHIFT && kb.vkCode != VK_RSHIFT {
+            if is_key_down {
+                SHIFT_RUN.store(0, Ordering::Relaxed);
+            }
+            return false;
+        }
+        if !is_key_down {
+            SHIFT_HELD.store(false, Ordering::Relaxed);
+            return SHIFT_SWALLOWED.swap(false, Ordering::Relaxed);
+        }
+        if SHIFT_HELD.swap(true, Ordering::Relaxed) {
+            return SHIFT_SWALLOWED.load(Ordering::Relaxed);
+        }
+
+        let gap = kb
+            .time
+            .wrapping_sub(LAST_SHIFT_TICK.swap(kb.time, Ordering::Relaxed));
+        let run = if gap <= SHIFT_RUN_GAP_MS {
+            SHIFT_RUN.load(Ordering::Relaxed) + 1
+        } else {
+            1
+        };
+        let swallow = run >= STICKY_KEYS_PRESSES;
+        SHIFT_RUN.store(if swallow { run - 1 } else { run }, Ordering::Relaxed);
+        SHIFT_SWALLOWED.store(swallow, Ordering::Relaxed);
+        swallow
+    }
+
+    /// Event time of the last left Ctrl press, see `ALTGR_DOWN`
+    static LAST_LCTRL_TICK: AtomicU32 = AtomicU32::new(0);
+
+    /// Whether right Alt is down as AltGr. On layouts with AltGr (German,
+    /// French...) Windows sends a fake left Ctrl press with the same event time
+    /// just before right Alt, so AltGr looks like Ctrl+Alt to the hook; on
+    /// layouts without it (US) right Alt is a plain Alt.
+    static ALTGR_DOWN: AtomicBool = AtomicBool::new(false);
+
+    /// Whether a right Alt event (`is_key_down`, at event time `time`) leaves
+    /// AltGr down, given whether it was already (`was_down`) and when left
+    /// Ctrl was last pressed. Auto-repeat sends the fake Ctrl again, but the
+    /// state is kept anyway in case a repeat arrives without it.
+    fn altgr_pressed(was_down: bool, is_key_down: bool, lctrl_tick: u32, time: u32) -> bool {
+        is_key_down && (was_down || lctrl_tick == time)
+    }
+
+    /// The modifiers the combo rules see for a key pressed with `held`. An
+    /// AltGr keystroke that `types_character` is text, not a Ctrl+Alt
+    /// shortcut, so no Ctrl or Alt rule applies to it. One that types nothing
+    /// keeps both modifiers and is blocked like any Ctrl+Alt (and so Ctrl)
+    /// combination. Right Ctrl held as well is a deliberate chord and isn't
+    /// exempted. `types_character` is only called for an AltGr chord.
+    fn altgr_modifiers(
+        mut held: HeldModifiers,
+        altgr_down: bool,
+        right_ctrl_held: bool,
+        types_character: impl FnOnce() -> bool,
+    ) -> HeldModifiers {
+        if held.ctrl && held.alt && altgr_down && !right_ctrl_held && types_character() {
+            held.ctrl = false;
+            held.alt = false;
+        }
+        held
+    }
+
+    /// Whether AltGr+`vk_code` types a character in the foreground window's
+    /// keyboard layout, e.g. @ for AltGr+Q on a German layout
+    fn altgr_types_character(vk_code: u32, scan_code: u32, shift: bool) -> bool {
+        let mut state = [0u8; 256];
+        for vk in [VK_CONTROL, VK_LCONTROL, VK_MENU, VK_RMENU] {
+            state[vk as usize] = 0x80;
+        }
+        if shift {
+            state[VK_SHIFT as usize] = 0x80;
+        }
+        let mut buffer = [0u16; 8];
+        unsafe {
+            let layout = GetKeyboardLayout(GetWindowThreadProcessId(GetForegroundWindow(), None));
+            // Flag 0x4 leaves the keyboard state alone (Windows 10 1607+), so
+            // a pending dead key still combines with the next real keystroke.
+            // A negative result is a dead key, which also types a character.
+            ToUnicodeEx(vk_code, scan_code, &state, &mut buffer, 0x4, Some(layout)) != 0
+        }
+    }
+
+    /// The Windows key `kb` represents, if any, as VK_LWIN or VK_RWIN.
+    ///
+    /// Some gaming and macro keyboards don't report the Windows key as
+    /// VK_LWIN/VK_RWIN: they send it through a virtual driver with a different
+    /// (or zero) virtual key code but the standard extended scan code (E0 5B /
+    /// E0 5C). Checking the scan code plus LLKHF_EXTENDED catches those.
+    fn windows_key(kb: &KBDLLHOOKSTRUCT) -> Option<u32> {
+        match kb.vkCode as i32 {
+            VK_LWIN | VK_RWIN => return Some(kb.vkCode),
+            _ => {}
+        }
+        if kb.flags.0 & LLKHF_EXTENDED == 0 {
+            return None;
+        }
+        match kb.scanCode {
+            SCAN_LWIN => Some(VK_LWIN as u32),
+            SCAN_RWIN => Some(VK_RWIN as u32),
+            _ => None,
+        }
+    }
+
+    struct BlockedCombo {
+        vk_code: u32,
+        action: KeyAction,
+        ctrl: bool,
+        alt: bool,
+        shift: bool,
+        win: bool,
+    }
+
+    impl BlockedCombo {
+        fn resolve(combo: &KeyCombo) -> Option<Self> {
+            let vk_code = vk_from_name(&combo.key)?;
+            let has = |modifier| combo.modifiers.contains(&modifier);
+            Some(Self {
+                vk_code,
+                action: combo.action,
+                ctrl: has(KeyModifier::Ctrl),
+                alt: has(KeyModifier::Alt),
+                shift: has(KeyModifier::Shift),
+                win: has(KeyModifier::Win),
+            })
+        }
+
+        /// `LWin` or `RWin` with no modifiers, i.e. the Windows key is blocked
+        fn is_bare_windows_key(&self) -> bool {
+            self.action == KeyAction::Block
+                && matches!(self.vk_code as i32, VK_LWIN | VK_RWIN)
+                && !(self.ctrl || self.alt || self.shift || self.win)
+        }
+
+        fn matches(&self, vk_code: u32, held: &HeldModifiers) -> bool {
+            self.vk_code == vk_code
+                && (!self.ctrl || held.ctrl)
+                && (!self.alt || held.alt)
+                && (!self.shift || held.shift)
+                && (!self.win || held.win)
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct HeldModifiers {
+        ctrl: bool,
+        alt: bool,
+        shift: bool,
+        win: bool,
+    }
+
+    /// Named keys accepted in `keyboard.blocked_combos` besides letters,
+    /// digits and F1-F24. The first name listed for a code is the one used
+    /// when reporting a blocked key.
+    const NAMED_KEYS: &[(&str, u32)] = &[
+        ("Tab", 0x09),
+        ("Enter", 0x0D),
+        ("Return", 0x0D),
+        ("Escape", 0x1B),
+        ("Esc", 0x1B),
+        ("Space", 0x20),
+        ("PrintScreen", 0x2C),
+        ("Snapshot", 0x2C),
+        ("Insert", 0x2D),
+        ("Delete", 0x2E),
+        ("LWin", 0x5B),
+        ("RWin", 0x5C),
+    ];
+
+    /// Map a config key name (case-insensitive) to its virtual key code.
+    fn vk_from_name(name: &str) -> Option<u32> {
+        if let Some(&(_, vk_code)) = NAMED_KEYS
+            .iter()
+            .find(|(named, _)| named.eq_ignore_ascii_case(name))
+        {
+            return Some(vk_code);
+        }
+
+        let name = name.to_ascii_uppercase();
+        let mut chars = name.chars();
+        match (chars.next(), chars.as_str()) {
+            (Some(c @ ('A'..='Z' | '0'..='9')), "") => Some(c as u32),
+            (Some('F'), number) => match number.parse::<u32>() {
+                Ok(n @ 1..=24) => Some(0x70 + n - 1),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Display name for a virtual key code, the inverse of `vk_from_name`
+    fn key_name(vk_code: u32) -> String {
+        if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, vk)| *vk == vk_code) {
+            return name.to_string();
+        }
+        match vk_code {
+            0x30..=0x39 | 0x41..=0x5A => char::from(vk_code as u8).to_string(),
+            0x70..=0x87 => format!("F{}", vk_code - 0x70 + 1),
+            _ => format!("VK 0x{vk_code:02X}"),
+        }
+    }
+
+    /// A key press the hook suppressed, or let through with a warning, queued
+    /// for `forward_blocked_keys`.
+    pub struct BlockedKey {
+        pub vk_code: u32,
+        pub at: Instant,
+        /// False for a `warn` combination, which reached the focused app
+        pub suppressed: bool,
+        held: HeldModifiers,
+    }
+
+    impl BlockedKey {
+        /// Symbolic name such as "Alt+Tab" or "Ctrl+Shift+Escape"
+        pub fn combo_name(&self) -> String {
+            let is_win_key = self.vk_code == VK_LWIN as u32 || self.vk_code == VK_RWIN as u32;
+            let mut name = String::new();
+            for (held, label) in [
+                (self.held.ctrl, "Ctrl+"),
+                (self.held.alt, "Alt+"),
+                (self.held.shift, "Shift+"),
+                (self.held.win && !is_win_key, "Win+"),
+            ] {
+                if held {
+                    name.push_str(label);
+                }
+            }
+            name.push_str(&key_name(self.vk_code));
+            name
+        }
+    }
+
+    /// Hook-side end of the blocked-key channel. Sending on an unbounded
+    /// channel never blocks, which keeps the hook callback fast.
+    static BLOCKED_KEY_SENDER: OnceLock<Sender<BlockedKey>> = OnceLock::new();
+
+    /// Start reporting suppressed keys. Returns None if already subscribed.
+    pub fn subscribe_blocked_keys() -> Option<Receiver<BlockedKey>> {
+        let (sender, receiver) = mpsc::channel();
+        BLOCKED_KEY_SENDER.set(sender).ok()?;
+        Some(receiver)
+    }
+
+    fn key_held(vk: i32) -> bool {
+        unsafe { (GetAsyncKeyState(vk) as u16 & 0x8000) != 0 }
+    }
+
+    /// The action for a key press: `allow` entries win over everything,
+    /// then `block` entries and the Windows key chord rule, then `warn`
+    /// entries. None lets the key through silently.
+    fn combo_action(
+        combos: &[BlockedCombo],
+        vk_code: u32,
+        win_key: Option<u32>,
+        held: &HeldModifiers,
+    ) -> Option<KeyAction> {
+        let matching = |action| {
+            combos
+                .iter()
+                .any(|c| c.action == action && c.matches(vk_code, held))
+        };
+        if matching(KeyAction::Allow) {
+            return None;
+        }
+        // With the Windows key itself blocked, also block anything pressed
+        // while it is held (Win+Tab, Win+D, Win+R...), in case the Win press
+        // slipped through before the hook was installed. Win+L is handled by
+        // the OS and can't be blocked from a hook.
+        let win_chord =
+            held.win && win_key.is_none() && combos.iter().any(BlockedCombo::is_bare_windows_key);
+        if matching(KeyAction::Block) || win_chord {
+            return Some(KeyAction::Block);
+        }
+        matching(KeyAction::Warn).then_some(KeyAction::Warn)
+    }
+
+    /// What `keyboard_hook_proc` does with a key press
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum KeyVerdict {
+        /// Hand it on with CallNextHookEx, unreported
+        Pass,
+        /// Hand it on with CallNextHookEx and report it as `warned-key`
+        Warn,
+        /// Swallow it and report it as `blocked-key`
+        Block,
+    }
+
+    impl KeyVerdict {
+        /// The press to queue for `forward_blocked_keys`, if it is reported
+        fn report(self, vk_code: u32, held: HeldModifiers) -> Option<BlockedKey> {
+            (self != Self::Pass).then(|| BlockedKey {
+                vk_code,
+                at: Instant::now(),
+                suppressed: self == Self::Block,
+                held,
+            })
+        }
+    }
+
+    /// Apply `combos`, `allowed_keys` and the paste exemption to a press of
+    /// `vk_code` (`win_key` for a Windows key) with `held` down. Only reads
+    /// its arguments, so the rules can be checked without a hook.
+    fn key_verdict(
+        combos: &[BlockedCombo],
+        allowed_keys: &[u32],
+        paste_allowed: bool,
+        vk_code: u32,
+        win_key: Option<u32>,
+        held: &HeldModifiers,
+    ) -> KeyVerdict {
+        // Checked before any block rule, so no combo or the Win chord rule
+        // can catch an exempted key
+        let unmodified = !held.ctrl && !held.alt && !held.win;
+        if unmodified && allowed_keys.contains(&vk_code) {
+            return KeyVerdict::Pass;
+        }
+        let paste = vk_code == VK_V && held.ctrl;
+        if paste && paste_allowed {
+            return KeyVerdict::Pass;
+        }
+        match combo_action(combos, vk_code, win_key, held) {
+            Some(KeyAction::Block) => KeyVerdict::Block,
+            Some(KeyAction::Warn) => KeyVerdict::Warn,
+            Some(KeyAction::Allow) | None => KeyVerdict::Pass,
+        }
+    }
+
+    /// Low-level keyboard hook callback. Applies the combinations configured
+    /// in `keyboard.blocked_combos` (by default blocking Alt+Tab, Alt+Esc,
+    /// Alt+F4, the Windows key, PrintScreen, Ctrl+C/V/P and F12).
+    unsafe extern "system" fn keyboard_hook_proc(
+        code: i32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if code >= 0 {
+            // Injected events (LLKHF_INJECTED, e.g. SendInput from another
+            // app or a keyboard's macro software) are filtered exactly like
+            // physical ones; they are deliberately not exempted.
+            let kb_struct = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+            if kb_struct.vkCode == VK_F24 as u32 && kb_struct.dwExtraInfo == SELF_TEST_TAG {
+                // GetLastInputInfo counts the injected press and release too;
+                // without this the periodic self-test would keep resetting
+                // the idle clock
+                LAST_BLOCKED_TICK.store(kb_struct.time, Ordering::Relaxed);
+                SELF_TEST_SEEN.store(true, Ordering::SeqCst);
+                return LRESULT(1);
+            }
+            let win_key = windows_key(kb_struct);
+            let vk_code = win_key.unwrap_or(kb_struct.vkCode);
+            let flags = kb_struct.flags.0;
+
+            let is_key_down = wparam.0 as u32 == WM_KEYDOWN || wparam.0 as u32 == WM_SYSKEYDOWN;
+            if GUARD_STICKY_KEYS.load(Ordering::Relaxed) && swallow_shift(kb_struct, is_key_down) {
+                return LRESULT(1);
+            }
+            if win_key.is_some() {
+                WIN_DOWN.store(is_key_down, Ordering::Relaxed);
+            }
+            if vk_code == VK_LCONTROL as u32 && is_key_down {
+                LAST_LCTRL_TICK.store(kb_struct.time, Ordering::Relaxed);
+            } else if vk_code == VK_RMENU as u32 {
+                let altgr = altgr_pressed(
+                    ALTGR_DOWN.load(Ordering::Relaxed),
+                    is_key_down,
+                    LAST_LCTRL_TICK.load(Ordering::Relaxed),
+                    kb_struct.time,
+                );
+                ALTGR_DOWN.store(altgr, Ordering::Relaxed);
+            }
+
+            if is_key_down {
+                let shift = key_held(VK_SHIFT);
+                let held = altgr_modifiers(
+                    HeldModifiers {
+                        ctrl: key_held(VK_CONTROL),
+                        alt: (flags & LLKHF_ALTDOWN) != 0,
+                        shift,
+                        win: WIN_DOWN.load(Ordering::Relaxed)
+                            || key_held(VK_LWIN)
+                            || key_held(VK_RWIN),
+                    },
+                    ALTGR_DOWN.load(Ordering::Relaxed),
+                    key_held(VK_RCONTROL),
+                    || altgr_types_character(vk_code, kb_struct.scanCode, shift),
+                );
+                let verdict = key_verdict(
+                    BLOCKED_COMBOS.get().map(Vec::as_slice).unwrap_or_default(),
+                    ALLOWED_KEYS.get().map(Vec::as_slice).unwrap_or_default(),
+                    PASTE_ALLOWED.load(Ordering::Relaxed),
+                    vk_code,
+                    win_key,
+                    &held,
+                );
+                if verdict == KeyVerdict::Block {
+                    LAST_BLOCKED_TICK.store(kb_struct.time, Ordering::Relaxed);
+                }
+                if let Some(key) = verdict.report(vk_code, held) {
+                    if let Some(sender) = BLOCKED_KEY_SENDER.get() {
+                        let _ = sender.send(key);
+                    }
+                }
+                if verdict == KeyVerdict::Block {
+                    return LRESULT(1);
+                }
+            }
+        }
+
+        // The hook handle is ignored by CallNextHookEx
+        CallNextHookEx(None, code, wparam, lparam)
+    }
+
+    /// Check that the hook really suppresses keys: inject an F24 press (no
+    /// app binds it) tagged with `SELF_TEST_TAG`, and pass only if the hook
+    /// saw it and it never reached the async key state. Group policy or
+    /// security software can leave a hook installed but ineffective.
+    pub fn run_self_test() -> bool {
+        let input = |flags| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: VIRTUAL_KEY(VK_F24),
+                    wScan: 0,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: SELF_TEST_TAG,
+                },
+            },
+        };
+        let size = std::mem::size_of::<INPUT>() as i32;
+
+        SELF_TEST_SEEN.store(false, Ordering::SeqCst);
+        let sent = unsafe { SendInput(&[input(KEYBD_EVENT_FLAGS(0))], size) } == 1;
+        let deadline = Instant::now() + SELF_TEST_TIMEOUT;
+        while sent && !SELF_TEST_SEEN.load(Ordering::SeqCst) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let leaked = key_held(VK_F24 as i32);
+        unsafe { SendInput(&[input(KEYEVENTF_KEYUP)], size) };
+
+        let passed = sent && SELF_TEST_SEEN.load(Ordering::SeqCst) && !leaked;
+        if !passed {
+            log::error!(
+                "keyboard hook self-test failed (sent: {sent}, seen: {}, leaked: {leaked})",
+                SELF_TEST_SEEN.load(Ordering::SeqCst)
+            );
+        }
+        SELF_TEST_RESULT.store(if passed { 1 } else { 2 }, Ordering::SeqCst);
+        passed
+    }
+
+    /// Outcome of the last `run_self_test`, if it has run
+    pub fn self_test_passed() -> Option<bool> {
+        match SELF_TEST_RESULT.load(Ordering::SeqCst) {
+            0 => None,
+            result => Some(result == 1),
+        }
+    }
+
+    pub fn set_paste_allowed(allowed: bool) {
+        PASTE_ALLOWED.store(allowed, Ordering::Relaxed);
+    }
+
+    /// Empty the clipboard, unless the page has paste enabled for a field (the
+    /// student is meant to paste the in-app passage they copied). Returns
+    /// false if the clipboard was skipped or is held open by another app.
+    pub fn clear_clipboard() -> bool {
+        if PASTE_ALLOWED.load(Ordering::Relaxed) {
+            return false;
+        }
+        unsafe {
+            if OpenClipboard(None).is_err() {
+                return false;
+            }
+            let cleared = EmptyClipboard().is_ok();
+            let _ = CloseClipboard();
+            cleared
+        }
+    }
+
+    pub fn is_hook_active() -> bool {
+        HOOK_ACTIVE.load(Ordering::SeqCst)
+    }
+
+    /// Install the low-level keyboard hook and wait (up to `INSTALL_TIMEOUT`)
+    /// until it is in place, so nothing slips through between this returning
+    /// and the hook existing. Returns false if it failed or didn't confirm in
+    /// time; `is_hook_active` then reports false once the thread gives up.
+    pub fn install_keyboard_hook(keyboard: &KeyboardConfig) -> bool {
+        if HOOK_ACTIVE.swap(true, Ordering::SeqCst) {
+            return true;
+        }
+
+        BLOCKED_COMBOS.get_or_init(|| {
+            keyboard
+                .blocked_combos
+                .iter()
+                .filter_map(|combo| {
+                    let resolved = BlockedCombo::resolve(combo);
+                    if resolved.is_none() {
+                        log::warn!("ignoring unknown blocked key: {}", combo.key);
+                    }
+                    resolved
+                })
+                .collect()
+        });
+        ALLOWED_KEYS.get_or_init(|| {
+            keyboard
+                .allowed_keys
+                .iter()
+                .filter_map(|key| {
+                    let vk_code = vk_from_name(key);
+                    if vk_code.is_none() {
+                        log::warn!("ignoring unknown allowed key: {key}");
+                    }
+                    vk_code
+                })
+                .collect()
+        });
+        if keyboard.block_accessibility_shortcuts {
+            GUARD_STICKY_KEYS.store(true, Ordering::SeqCst);
+            disable_accessibility_shortcuts();
+        }
+
+        spawn_hook_thread()
+    }
+
+    /// Accessibility settings as they were before
+    /// `disable_accessibility_shortcuts`, for `restore_accessibility_shortcuts`
+    static SAVED_ACCESSIBILITY: Mutex<Option<(STICKYKEYS, FILTERKEYS, TOGGLEKEYS)>> =
+        Mutex::new(None);
+
+    /// Read or write one of the `*KEYS` structs with SystemParametersInfoW
+    fn accessibility_setting<T>(action: SYSTEM_PARAMETERS_INFO_ACTION, value: &mut T) -> bool {
+        let size = std::mem::size_of::<T>() as u32;
+        unsafe {
+            SystemParametersInfoW(
+                action,
+                size,
+                Some(value as *mut T as *mut _),
+                // Not written to the profile, so a crash only lasts until logoff
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+            .is_ok()
+        }
+    }
+
+    /// Turn off the Sticky Keys, Filter Keys and Toggle Keys shortcuts for
+    /// this session. Win+Plus (Magnifier) needs nothing extra: it is caught
+    /// by the Windows key chord rule.
+    fn disable_accessibility_shortcuts() {
+        let mut sticky = STICKYKEYS {
+            cbSize: std::mem::size_of::<STICKYKEYS>() as u32,
+            ..Default::default()
+        };
+        let mut filter = FILTERKEYS {
+            cbSize: std::mem::size_of::<FILTERKEYS>() as u32,
+            ..Default::default()
+        };
+        let mut toggle = TOGGLEKEYS {
+            cbSize: std::mem::size_of::<TOGGLEKEYS>() as u32,
+            ..Default::default()
+        };
+        if !(accessibility_setting(SPI_GETSTICKYKEYS, &mut sticky)
+            && accessibility_setting(SPI_GETFILTERKEYS, &mut filter)
+            && accessibility_setting(SPI_GETTOGGLEKEYS, &mut toggle))
+        {
+            log::warn!("failed to read the accessibility shortcut settings");
+            return;
+        }
+        let mut saved = SAVED_ACCESSIBILITY.lock().unwrap();
+        if saved.is_none() {
+            *saved = Some((sticky, filter, toggle));
+        }
+
+        sticky.dwFlags.0 &= !SKF_HOTKEYACTIVE.0;
+        filter.dwFlags.0 &= !FKF_HOTKEYACTIVE.0;
+        toggle.dwFlags.0 &= !TKF_HOTKEYACTIVE.0;
+        if !(accessibility_setting(SPI_SETSTICKYKEYS, &mut sticky)
+            && accessibility_setting(SPI_SETFILTERKEYS, &mut filter)
+            && accessibility_setting(SPI_SETTOGGLEKEYS, &mut toggle))
+        {
+            log::warn!("failed to disable the accessibility shortcuts");
+        }
+    }
+
+    /// Put back the settings `disable_accessibility_shortcuts` changed
+    pub fn restore_accessibility_shortcuts() {
+        let Some((mut sticky, mut filter, mut toggle)) = SAVED_ACCESSIBILITY.lock().unwrap().take()
+        else {
+            return;
+        };
+        if !(accessibility_setting(SPI_SETSTICKYKEYS, &mut sticky)
+            && accessibility_setting(SPI_SETFILTERKEYS, &mut filter)
+            && accessibility_setting(SPI_SETTOGGLEKEYS, &mut toggle))
+        {
+            log::warn!("failed to restore the accessibility shortcuts");
+        }
+    }
+
+    /// Reinstall the hook after `uninstall_keyboard_hook`, blocking the
+    /// combinations resolved by the first `install_keyboard_hook`. Waits like
+    /// `install_keyboard_hook`.
+    pub fn reinstall_keyboard_hook() -> bool {
+        if HOOK_ACTIVE.swap(true, Ordering::SeqCst) {
+            return true;
+        }
+        spawn_hook_thread()
+    }
+
+    fn spawn_hook_thread() -> bool {
+        let started = Instant::now();
+        let (installed_tx, installed_rx) = std::sync::mpsc::channel();
+        thread::spawn(move || unsafe {
+            // Create this thread's message queue before publishing its id so
+            // a WM_QUIT posted by uninstall_keyboard_hook can't be dropped
+            let mut msg = MSG::default();
+            let _ = PeekMessageW(&mut msg, None, 0, 0, PM_NOREMOVE);
+            HOOK_THREAD_ID.store(GetCurrentThreadId(), Ordering::SeqCst);
+
+            let h_module = GetModuleHandleW(PCWSTR::null()).unwrap_or_default();
+            let h_instance = HINSTANCE(h_module.0);
+
+            let hook = SetWindowsHookExW(
+                WH_KEYBOARD_LL,
+                Some(keyboard_hook_proc),
+                Some(h_instance),
+                0,
+            );
+            if let Err(error) = &hook {
+                log::error!("SetWindowsHookExW failed: {error}");
+            }
+            let _ = installed_tx.send(hook.is_ok());
+
+            if let Ok(hook) = hook {
+                // Message loop to keep hook alive
+                while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                    let _ = TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+
+                // Cleanup on exit
+                let _ = UnhookWindowsHookEx(hook);
+            }
+            HOOK_THREAD_ID.store(0, Ordering::SeqCst);
+            HOOK_ACTIVE.store(false, Ordering::SeqCst);
+        });
+
+        let installed = installed_rx
+            .recv_timeout(INSTALL_TIMEOUT)
+            .unwrap_or_else(|_| {
+                log::error!("keyboard hook did not confirm within {INSTALL_TIMEOUT:?}");
+                false
+            });
+        if installed {
+            let elapsed = started.elapsed();
+            HOOK_INSTALL_MS.store(elapsed.as_millis() as u64, Ordering::SeqCst);
+            if elapsed > SLOW_INSTALL {
+                log::warn!("keyboard hook took {elapsed:?} to install");
+            } else {
+                log::info!("keyboard hook installed in {elapsed:?}");
+            }
+        }
+        installed
+    }
+
+    /// How long the last successful hook install took, in milliseconds
+    pub fn hook_install_ms() -> Option<u64> {
+        match HOOK_INSTALL_MS.load(Ordering::SeqCst) {
+            u64::MAX => None,
+            ms => Some(ms),
+        }
+    }
+
+    /// Stop the hook thread's message loop and wait for it to unhook, so a
+    /// later `install_keyboard_hook` installs a fresh hook. Returns false if
+    /// the thread didn't finish within `UNINSTALL_TIMEOUT`.
+    pub fn uninstall_keyboard_hook() -> bool {
+        let deadline = Instant::now() + UNINSTALL_TIMEOUT;
+        let mut quit_posted = false;
+
+        while HOOK_ACTIVE.load(Ordering::SeqCst) {
+            if !quit_posted {
+                let thread_id = HOOK_THREAD_ID.load(Ordering::SeqCst);
+                if thread_id != 0 {
+                    quit_posted = unsafe {
+                        PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0)).is_ok()
+                    };
+                }
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        true
+    }
+
+    /// Longest window title reported by `foreground_window`, in characters
+    const MAX_WINDOW_TITLE_LEN: usize = 200;
+
+    /// Title and executable name of the window that currently has the
+    /// foreground. Either is None if the window has no title or its process
+    /// can't be opened (e.g. an elevated process).
+    pub fn foreground_window() -> (Option<String>, Option<String>) {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.0.is_null() {
+                return (None, None);
+            }
+
+            let mut title = [0u16; MAX_WINDOW_TITLE_LEN + 1];
+            let len = GetWindowTextW(hwnd, &mut title).max(0) as usize;
+            let title = (len > 0).then(|| String::from_utf16_lossy(&title[..len]));
+
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid)
+                .ok()
+                .and_then(|handle| {
+                    let name = process_image_name(handle);
+                    let _ = CloseHandle(handle);
+                    name
+                });
+
+            (title, process)
+        }
+    }
+
+    /// Executable name of the process `handle` was opened on, which needs
+    /// PROCESS_QUERY_LIMITED_INFORMATION
+    fn process_image_name(handle: HANDLE) -> Option<String> {
+        let mut path = [0u16; 1024];
+        let mut size = path.len() as u32;
+        unsafe {
+            QueryFullProcessImageNameW(
+                handle,
+                PROCESS_NAME_WIN32,
+                PWSTR(path.as_mut_ptr()),
+                &mut size,
+            )
+            .ok()?;
+        }
+        let path = String::from_utf16_lossy(&path[..size as usize]);
+        path.rsplit('\\').next().map(str::to_owned)
+    }
+
+    /// Process id and executable name of every running process, from a
+    /// Toolhelp snapshot
+    pub fn running_processes() -> Vec<(u32, String)> {
+        let mut processes = Vec::new();
+        unsafe {
+            let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else {
+                return processes;
+            };
+            let mut entry = PROCESSENTRY32W {
+                dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+                ..Default::default()
+            };
+            let mut found = Process32FirstW(snapshot, &mut entry).is_ok();
+            while found {
+                let name = &entry.szExeFile;
+                let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+                processes.push((entry.th32ProcessID, String::from_utf16_lossy(&name[..len])));
+                found = Process32NextW(snapshot, &mut entry).is_ok();
+            }
+            let _ = CloseHandle(snapshot);
+        }
+        processes
+    }
+
+    /// Executable names of every running process, from a Toolhelp snapshot
+    pub fn running_process_names() -> Vec<String> {
+        running_processes()
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect()
+    }
+
+    /// Terminate process `pid` if `allowed` accepts its executable name,
+    /// returning the name. The name is read from the opened handle rather
+    /// than an earlier snapshot, so a pid reused by another process in the
+    /// meantime is checked as that process.
+    pub fn terminate_process_if(
+        pid: u32,
+        allowed: impl Fn(&str) -> bool,
+    ) -> Result<String, String> {
+        unsafe {
+            let handle = OpenProcess(
+                PROCESS_TERMINATE | PROCESS_QUERY_LIMITED_INFORMATION,
+                false,
+                pid,
+            )
+            .map_err(|error| format!("can't open process {pid}: {error}"))?;
+            let result =
+                termination_allowed(pid, process_image_name(handle), allowed).and_then(|name| {
+                    TerminateProcess(handle, 1)
+                        .map(|()| name)
+                        .map_err(|error| format!("can't terminate process {pid}: {error}"))
+                });
+            let _ = CloseHandle(handle);
+            result
+        }
+    }
+
+    /// The executable name of `pid` if `allowed` accepts it, else why
+    /// `terminate_process_if` refuses it
+    fn termination_allowed(
+        pid: u32,
+        name: Option<String>,
+        allowed: impl Fn(&str) -> bool,
+    ) -> Result<String, String> {
+        match name {
+            Some(name) if allowed(&name) => Ok(name),
+            Some(name) => Err(format!("{name} ({pid}) is not on the blocklist")),
+            None => Err(format!("can't identify process {pid}")),
+        }
+    }
+
+    /// Device interface path of a Raw Input device, e.g.
+    /// `\\?\HID#VID_046D&PID_C52B&MI_00#8&2d3e4f5&0&0000#{884b96c3-...}`
+    fn raw_input_device_name(device: HANDLE) -> Option<String> {
+        let mut len = 0u32;
+        unsafe {
+            GetRawInputDeviceInfoW(Some(device), RIDI_DEVICENAME, None, &mut len);
+            let mut name = vec![0u16; len as usize];
+            let copied = GetRawInputDeviceInfoW(
+                Some(device),
+                RIDI_DEVICENAME,
+                Some(name.as_mut_ptr().cast()),
+                &mut len,
+            );
+            if copied == 0 || copied == u32::MAX {
+                return None;
+            }
+            let end = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+            Some(String::from_utf16_lossy(&name[..end]))
+        }
+    }
+
+    /// The part of a device path that is the same for every logical device
+    /// one piece of hardware exposes: the bus and hardware ID, without the
+    /// interface (`MI_01`) and top-level collection (`Col02`) a wireless or
+    /// Bluetooth receiver adds for each of its keyboards and mice. Devices
+    /// with no hardware ID (e.g. `ACPI#...`) keep their instance path.
+    ///
+    /// Two devices of the same model share a key, so an identical second
+    /// keyboard isn't noticed.
+    fn physical_device_key(path: &str) -> String {
+        let path = path.to_ascii_lowercase();
+        let mut parts = path.trim_start_matches(r"\\?\").split('#');
+        let bus = parts.next().unwrap_or_default();
+        let hardware_id = parts.next().unwrap_or_default();
+        if !hardware_id.contains("vid") {
+            return path;
+        }
+        let hardware_id: Vec<&str> = hardware_id
+            .split('&')
+            .filter(|part| !part.starts_with("mi_") && !part.starts_with("col"))
+            .collect();
+        format!("{bus}#{}", hardware_id.join("&"))
+    }
+
+    /// Physical keyboards and mice attached, from the Raw Input device list.
+    /// None if the list can't be read, or changed while it was being read.
+    pub fn input_devices() -> Option<InputDevices> {
+        let entry_size = std::mem::size_of::<RAWINPUTDEVICELIST>() as u32;
+        let mut count = 0u32;
+        let list = unsafe {
+            if GetRawInputDeviceList(None, &mut count, entry_size) == u32::MAX {
+                return None;
+            }
+            let mut list = vec![RAWINPUTDEVICELIST::default(); count as usize];
+            let listed = GetRawInputDeviceList(Some(list.as_mut_ptr()), &mut count, entry_size);
+            if listed == u32::MAX {
+                return None;
+            }
+            list.truncate(listed as usize);
+            list
+        };
+
+        let mut keyboards = HashSet::new();
+        let mut mice = HashSet::new();
+        for device in list {
+            let devices = match device.dwType {
+                RIM_TYPEKEYBOARD => &mut keyboards,
+                RIM_TYPEMOUSE => &mut mice,
+                _ => continue,
+            };
+            if let Some(path) = raw_input_device_name(device.hDevice) {
+                devices.insert(physical_device_key(&path));
+            }
+        }
+        Some(InputDevices {
+            keyboards: keyboards.len(),
+            mice: mice.len(),
+        })
+    }
+
+    /// Battery state from GetSystemPowerStatus. None if it can't be read
+    pub fn power_status() -> Option<PowerStatus> {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        unsafe { GetSystemPowerStatus(&mut status) }.ok()?;
+        // 255 and u32::MAX mean unknown, e.g. a desktop without a battery
+        Some(PowerStatus {
+            percent: (status.BatteryLifePercent != 255).then_some(status.BatteryLifePercent),
+            charging: status.ACLineStatus == 1,
+            seconds_remaining: (status.BatteryLifeTime != u32::MAX)
+                .then_some(status.BatteryLifeTime),
+        })
+    }
+
+    unsafe extern "system" fn collect_monitor(
+        monitor: HMONITOR,
+        _hdc: HDC,
+        _clip: *mut RECT,
+        data: LPARAM,
+    ) -> BOOL {
+        let displays = &mut *(data.0 as *mut Vec<DisplayInfo>);
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+        if GetMonitorInfoW(
+            monitor,
+            &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+        )
+        .as_bool()
+        {
+            let rect = info.monitorInfo.rcMonitor;
+            let (mut dpi_x, mut dpi_y) = (USER_DEFAULT_SCREEN_DPI, USER_DEFAULT_SCREEN_DPI);
+            let _ = GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y);
+            let name = &info.szDevice;
+            let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+            displays.push(DisplayInfo {
+                index: displays.len(),
+                bounds: DisplayBounds {
+                    left: rect.left,
+                    top: rect.top,
+                    right: rect.right,
+                    bottom: rect.bottom,
+                },
+                primary: info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+                scale_factor: f64::from(dpi_x) / f64::from(USER_DEFAULT_SCREEN_DPI),
+                device_name: String::from_utf16_lossy(&name[..len]),
+                mirror_of: None,
+            });
+        }
+        TRUE
+    }
+
+    /// DPI at a scale factor of 1.0
+    const USER_DEFAULT_SCREEN_DPI: u32 = 96;
+
+    /// Every attached display in enumeration order, with bounds in virtual
+    /// screen coordinates (physical pixels, since the process is per-monitor
+    /// DPI aware), each display's own effective scale factor, and GDI device
+    /// names such as `\\.\DISPLAY1`
+    pub fn displays() -> Vec<DisplayInfo> {
+        let mut displays: Vec<DisplayInfo> = Vec::new();
+        unsafe {
+            let _ = EnumDisplayMonitors(
+                None,
+                None,
+                Some(collect_monitor),
+                LPARAM(&mut displays as *mut Vec<DisplayInfo> as isize),
+            );
+        }
+        displays
+    }
+
+    /// Milliseconds since boot, wrapping every ~49.7 days
+    pub fn tick_count() -> u32 {
+        unsafe { GetTickCount() }
+    }
+
+    /// Tick of the last keyboard or mouse input the system saw, including
+    /// key presses this hook suppressed and the keystrokes `run_self_test`
+    /// injects; compare with `last_blocked_tick` to tell them apart
+    pub fn last_input_tick() -> Option<u32> {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        unsafe { GetLastInputInfo(&mut info) }
+            .as_bool()
+            .then_some(info.dwTime)
+    }
+
+    /// Tick of the last key press this hook suppressed or self-test
+    /// keystroke it swallowed
+    pub fn last_blocked_tick() -> u32 {
+        LAST_BLOCKED_TICK.load(Ordering::Relaxed)
+    }
+
+    // Flag from MSLLHOOKSTRUCT
+    const LLMHF_INJECTED: u32 = 0x01;
+
+    /// A pointer move seen by the low-level mouse hook
+    pub struct MouseMove {
+        pub x: i32,
+        pub y: i32,
+        /// Event time (GetTickCount ms)
+        pub time: u32,
+        /// Sent with SendInput or similar rather than by a device
+        pub injected: bool,
+    }
+
+    /// Hook-side end of the mouse move channel, see `BLOCKED_KEY_SENDER`
+    static MOUSE_MOVE_SENDER: OnceLock<Sender<MouseMove>> = OnceLock::new();
+
+    /// Low-level mouse hook callback. Only reports moves; nothing is blocked.
+    unsafe extern "system" fn mouse_hook_proc(
+        code: i32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if code >= 0 && wparam.0 as u32 == WM_MOUSEMOVE {
+            let ms_struct = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+            if let Some(sender) = MOUSE_MOVE_SENDER.get() {
+                let _ = sender.send(MouseMove {
+                    x: ms_struct.pt.x,
+                    y: ms_struct.pt.y,
+                    time: ms_struct.time,
+                    injected: ms_struct.flags & LLMHF_INJECTED != 0,
+                });
+            }
+        }
+        CallNextHookEx(None, code, wparam, lparam)
+    }
+
+    /// Install a low-level mouse hook for the life of the app and return its
+    /// moves. Returns None if already subscribed or the hook failed.
+    pub fn subscribe_mouse_moves() -> Option<Receiver<MouseMove>> {
+        let (sender, receiver) = mpsc::channel();
+        MOUSE_MOVE_SENDER.set(sender).ok()?;
+
+        let (installed_tx, installed_rx) = mpsc::channel();
+        thread::spawn(move || unsafe {
+            let h_module = GetModuleHandleW(PCWSTR::null()).unwrap_or_default();
+            let hook = SetWindowsHookExW(
+                WH_MOUSE_LL,
+                Some(mouse_hook_proc),
+                Some(HINSTANCE(h_module.0)),
+                0,
+            );
+            if let Err(error) = &hook {
+                log::error!("mouse hook failed: {error}");
+            }
+            let _ = installed_tx.send(hook.is_ok());
+            if hook.is_ok() {
+                let mut msg = MSG::default();
+                while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                    let _ = TranslateMessage(&msg);
+                    DispatchMessageW(&msg);
+                }
+            }
+        });
+        installed_rx
+            .recv_timeout(INSTALL_TIMEOUT)
+            .unwrap_or(false)
+            .then_some(receiver)
+    }
+
+    // Virtual machine heuristics for `virtual_machine_artifact`. Each entry
+    // is one signal; trim these lists if a legitimate deployment (e.g. VDI on
+    // VMware Horizon) trips them.
+
+    /// Hypervisor vendor IDs (CPUID leaf 0x40000000) of guest VMs. Hyper-V's
+    /// "Microsoft Hv" is left out: Windows reports it on bare metal too once
+    /// virtualization-based security, WSL2 or Hyper-V itself is enabled, so
+    /// the CPUID hypervisor bit alone is not treated as a VM either.
+    const VM_HYPERVISOR_VENDORS: &[(&str, &str)] = &[
+        ("VMwareVMware", "VMware"),
+        ("VBoxVBoxVBox", "VirtualBox"),
+        ("KVMKVMKVM", "KVM"),
+        ("XenVMMXenVMM", "Xen"),
+        ("prl hyperv", "Parallels"),
+        ("TCGTCGTCGTCG", "QEMU"),
+    ];
+
+    /// HKLM keys left by guest additions/tools
+    const VM_REGISTRY_KEYS: &[&str] = &[
+        r"SOFTWARE\VMware, Inc.\VMware Tools",
+        r"SOFTWARE\Oracle\VirtualBox Guest Additions",
+        r"SYSTEM\CurrentControlSet\Services\VBoxGuest",
+        r"SYSTEM\CurrentControlSet\Services\VMTools",
+        r"SYSTEM\CurrentControlSet\Services\prl_tg",
+    ];
+
+    /// Guest drivers in %SystemRoot%\System32\drivers
+    const VM_DRIVER_FILES: &[&str] = &[
+        "vmmouse.sys",
+        "vmhgfs.sys",
+        "vm3dmp.sys",
+        "VBoxMouse.sys",
+        "VBoxGuest.sys",
+        "VBoxSF.sys",
+        "prl_fs.sys",
+    ];
+
+    /// Vendor ID from CPUID leaf 0x40000000, if a hypervisor is present
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn hypervisor_vendor() -> Option<String> {
+        #[cfg(target_arch = "x86")]
+        use std::arch::x86::__cpuid;
+        #[cfg(target_arch = "x86_64")]
+        use std::arch::x86_64::__cpuid;
+
+        // Leaf 1, ECX bit 31: hypervisor present
+        let features = __cpuid(1);
+        if features.ecx & (1 << 31) == 0 {
+            return None;
+        }
+        let leaf = __cpuid(0x4000_0000);
+        let bytes: Vec<u8> = [leaf.ebx, leaf.ecx, leaf.edx]
+            .iter()
+            .flat_map(|register| register.to_le_bytes())
+            .collect();
+        Some(
+            String::from_utf8_lossy(&bytes)
+                .trim_end_matches(['\0', ' '])
+                .to_owned(),
+        )
+    }
+
+    /// CPUID is only checked on x86; ARM64 relies on the other signals
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    fn hypervisor_vendor() -> Option<String> {
+        None
+    }
+
+    fn registry_key_exists(path: &str) -> bool {
+        let mut key = HKEY::default();
+        unsafe {
+            let opened = RegOpenKeyExW(
+                HKEY_LOCAL_MACHINE,
+                &HSTRING::from(path),
+                Some(0),
+                KEY_READ,
+                &mut key,
+            )
+            .is_ok();
+            if opened {
+                let _ = RegCloseKey(key);
+            }
+            opened
+        }
+    }
+
+    /// The first virtual machine artifact found, described for the error
+    /// message, or None on what looks like physical hardware
+    pub fn virtual_machine_artifact() -> Option<String> {
+        if let Some(vendor) = hypervisor_vendor() {
+            if let Some((_, name)) = VM_HYPERVISOR_VENDORS.iter().find(|(id, _)| vendor == *id) {
+                return Some(format!("{name} hypervisor"));
+            }
+        }
+
+        if let Some(key) = VM_REGISTRY_KEYS.iter().find(|key| registry_key_exists(key)) {
+            return Some(format!("registry key HKLM\\{key}"));
+        }
+
+        let drivers = std::path::Path::new(
+            &std::env::var_os("SystemRoot").unwrap_or_else(|| r"C:\Windows".into()),
+        )
+        .join("System32")
+        .join("drivers");
+        VM_DRIVER_FILES
+            .iter()
+            .find(|file| drivers.join(file).exists())
+            .map(|file| format!("driver {file}"))
+    }
+
+    /// Blank the window in screen captures (Snipping Tool, PrintScreen,
+    /// recorders, remote-desktop streams) even if a capture shortcut gets
+    /// through, or restore normal capture. WDA_EXCLUDEFROMCAPTURE needs
+    /// Windows 10 2004+; older builds fall back to WDA_MONITOR, which shows
+    /// the window as a black rectangle instead.
+    pub fn set_capture_excluded(
+        window: &tauri::WebviewWindow,
+        excluded: bool,
+    ) -> Result<(), String> {
+        let hwnd = HWND(window.hwnd().map_err(|error| error.to_string())?.0);
+        unsafe {
+            if !excluded {
+                return SetWindowDisplayAffinity(hwnd, WDA_NONE).map_err(|error| error.to_string());
+            }
+            if SetWindowDisplayAffinity(hwnd, WDA_EXCLUDEFROMCAPTURE).is_ok() {
+                return Ok(());
+            }
+            log::info!("WDA_EXCLUDEFROMCAPTURE unsupported; using WDA_MONITOR");
+            SetWindowDisplayAffinity(hwnd, WDA_MONITOR).map_err(|error| error.to_string())
+        }
+    }
+
+    /// PrintWindow flag (not exported by the windows crate) that has the
+    /// window render DirectComposition content such as WebView2 into the DC;
+    /// without it the webview area comes out black
+    const PW_RENDERFULLCONTENT: u32 = 0x2;
+
+    /// The window's client area as top-down RGB rows, plus its width and
+    /// height. PrintWindow has the window paint itself into our bitmap rather
+    /// than reading the screen, so the display affinity set by
+    /// `set_capture_excluded` (which only applies to screen captures) doesn't
+    /// blank it, and the affinity never has to be lifted for other apps.
+    pub fn capture_window(window: &tauri::WebviewWindow) -> Result<(u32, u32, Vec<u8>), String> {
+        let hwnd = HWND(window.hwnd().map_err(|error| error.to_string())?.0);
+        unsafe {
+            let mut rect = RECT::default();
+            GetClientRect(hwnd, &mut rect).map_err(|error| error.to_string())?;
+            let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+            if width <= 0 || height <= 0 {
+                return Err("the window has no visible area".into());
+            }
+
+            let screen_dc = GetDC(Some(hwnd));
+            let memory_dc = CreateCompatibleDC(Some(screen_dc));
+            let bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+            let previous = SelectObject(memory_dc, bitmap.into());
+            let printed = PrintWindow(
+                hwnd,
+                memory_dc,
+                PRINT_WINDOW_FLAGS(PW_CLIENTONLY.0 | PW_RENDERFULLCONTENT),
+            )
+            .as_bool();
+            // GetDIBits needs the bitmap deselected first
+            SelectObject(memory_dc, previous);
+
+            let mut info = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: width,
+                    // Negative height asks for top-down rows
+                    biHeight: -height,
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut bgra = vec![0u8; width as usize * height as usize * 4];
+            let rows = if printed {
+                GetDIBits(
+                    memory_dc,
+                    bitmap,
+                    0,
+                    height as u32,
+                    Some(bgra.as_mut_ptr().cast()),
+                    &mut info,
+                    DIB_RGB_COLORS,
+                )
+            } else {
+                0
+            };
+
+            let _ = DeleteObject(bitmap.into());
+            let _ = DeleteDC(memory_dc);
+            ReleaseDC(Some(hwnd), screen_dc);
+
+            if !printed {
+                return Err("PrintWindow failed".into());
+            }
+            if rows != height {
+                return Err("GetDIBits failed".into());
+            }
+            let rgb = bgra
+                .chunks_exact(4)
+                .flat_map(|pixel| [pixel[2], pixel[1], pixel[0]])
+                .collect();
+            Ok((width as u32, height as u32, rgb))
+        }
+    }
+
+    /// Registry key and value behind "Show the touch keyboard when not in
+    /// tablet mode and there's no keyboard attached" (Windows 10 1803+ and
+    /// Windows 11)
+    const TABLET_TIP_KEY: &str = r"Software\Microsoft\TabletTip\1.7";
+    const AUTO_INVOKE_VALUE: &str = "EnableDesktopModeAutoInvoke";
+
+    /// The auto-invoke setting before `suppress_touch_keyboard`: None until
+    /// it runs, then the previous value (None if it wasn't set)
+    static SAVED_AUTO_INVOKE: Mutex<Option<Option<u32>>> = Mutex::new(None);
+
+    fn auto_invoke_setting() -> Option<u32> {
+        let mut value = 0u32;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                &HSTRING::from(TABLET_TIP_KEY),
+                &HSTRING::from(AUTO_INVOKE_VALUE),
+                RRF_RT_REG_DWORD,
+                None,
+                Some(&mut value as *mut u32 as *mut _),
+                Some(&mut size),
+            )
+        }
+        .is_ok()
+        .then_some(value)
+    }
+
+    fn set_auto_invoke_setting(value: Option<u32>) -> bool {
+        let key = HSTRING::from(TABLET_TIP_KEY);
+        let name = HSTRING::from(AUTO_INVOKE_VALUE);
+        unsafe {
+            match value {
+                Some(value) => RegSetKeyValueW(
+                    HKEY_CURRENT_USER,
+                    &key,
+                    &name,
+                    REG_DWORD.0,
+                    Some(&value as *const u32 as *const _),
+                    std::mem::size_of::<u32>() as u32,
+                ),
+                None => RegDeleteKeyValueW(HKEY_CURRENT_USER, &key, &name),
+            }
+        }
+        .is_ok()
+    }
+
+    /// Stop the touch keyboard opening by itself for a tapped text field.
+    /// Only the desktop-mode setting can be changed per user; in tablet mode
+    /// (Windows 10) the keyboard still opens, which `touch_keyboard_visible`
+    /// catches. The setting lives in the user's profile, so a crash leaves it
+    /// off until it is switched back on in Settings.
+    pub fn suppress_touch_keyboard() {
+        let mut saved = SAVED_AUTO_INVOKE.lock().unwrap();
+        let previous = auto_invoke_setting();
+        if !set_auto_invoke_setting(Some(0)) {
+            log::warn!("failed to turn off touch keyboard auto-invoke");
+            return;
+        }
+        saved.get_or_insert(previous);
+    }
+
+    /// Put back the setting `suppress_touch_keyboard` changed
+    pub fn restore_touch_keyboard() {
+        if let Some(previous) = SAVED_AUTO_INVOKE.lock().unwrap().take() {
+            if !set_auto_invoke_setting(previous) {
+                log::warn!("failed to restore touch keyboard auto-invoke");
+            }
+        }
+    }
+
+    /// Whether the touch keyboard is on screen: TabTip's window on Windows
+    /// 10, the "Windows Input Experience" core window on Windows 11 (matched
+    /// by its English title, so other display languages aren't detected).
+    /// Both windows exist while hidden, so only an enabled, visible and (for
+    /// the core window) uncloaked one counts.
+    pub fn touch_keyboard_visible() -> bool {
+        unsafe {
+            if let Ok(tabtip) = FindWindowW(&HSTRING::from("IPTip_Main_Window"), PCWSTR::null()) {
+                let style = GetWindowLongW(tabtip, GWL_STYLE) as u32;
+                if IsWindowVisible(tabtip).as_bool() && style & WS_DISABLED.0 == 0 {
+                    return true;
+                }
+            }
+            let Ok(input) = FindWindowW(
+                &HSTRING::from("Windows.UI.Core.CoreWindow"),
+                &HSTRING::from("Windows Input Experience"),
+            ) else {
+                return false;
+            };
+            let mut cloaked = 0u32;
+            let read = DwmGetWindowAttribute(
+                input,
+                DWMWA_CLOAKED,
+                &mut cloaked as *mut u32 as *mut _,
+                std::mem::size_of::<u32>() as u32,
+            )
+            .is_ok();
+            IsWindowVisible(input).as_bool() && read && cloaked == 0
+        }
+    }
+
+    /// Move the window back to the top of the topmost band without
+    /// activating it, so it doesn't steal focus from whatever is focused
+    pub fn raise_topmost(window: &tauri::WebviewWindow) {
+        if let Ok(hwnd) = window.hwnd() {
+            unsafe {
+                let _ = SetWindowPos(
+                    HWND(hwnd.0),
+                    Some(HWND_TOPMOST),
+                    0,
+                    0,
+                    0,
+                    0,
+                    SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+                );
+            }
+        }
+    }
+
+    /// Answer every request the main webview makes (documents, scripts,
+    /// images, fetch and XHR) that `allow` refuses with an empty 403.
+    /// WebSockets never reach this filter.
+    pub fn filter_web_requests(
+        window: &tauri::WebviewWindow,
+        allow: impl Fn(&str) -> bool + Send + 'static,
+    ) -> Result<(), String> {
+        window
+            .with_webview(move |webview| {
+                if let Err(error) = add_request_filter(&webview, allow) {
+                    log::error!("failed to filter web requests: {error}");
+                }
+            })
+            .map_err(|error| error.to_string())
+    }
+
+    fn add_request_filter(
+        webview: &tauri::webview::PlatformWebview,
+        allow: impl Fn(&str) -> bool + 'static,
+    ) -> windows::core::Result<()> {
+        let environment = webview.environment();
+        let handler = WebResourceRequestedEventHandler::create(Box::new(move |_, args| {
+            let Some(args) = args else {
+                return Ok(());
+            };
+            let mut uri = PWSTR::null();
+            unsafe {
+                args.Request()?.Uri(&mut uri)?;
+                if allow(&take_pwstr(uri)) {
+                    return Ok(());
+                }
+                let response = environment.CreateWebResourceResponse(
+                    None,
+                    403,
+                    &HSTRING::from("Forbidden"),
+                    &HSTRING::new(),
+                )?;
+                args.SetResponse(&response)
+            }
+        }));
+        unsafe {
+            let core = webview.controller().CoreWebView2()?;
+            core.AddWebResourceRequestedFilter(
+                &HSTRING::from("*"),
+                COREWEBVIEW2_WEB_RESOURCE_CONTEXT_ALL,
+            )?;
+            let mut token = 0;
+            core.add_WebResourceRequested(&handler, &mut token)
+        }
+    }
+
+    /// Whether this executable's Authenticode signature is intact and chains
+    /// to a trusted root, or `None` when it isn't signed at all
+    pub fn authenticode_valid() -> Option<bool> {
+        let path = HSTRING::from(std::env::current_exe().ok()?.as_os_str());
+        let mut file = WINTRUST_FILE_INFO {
+            cbStruct: std::mem::size_of::<WINTRUST_FILE_INFO>() as u32,
+            pcwszFilePath: PCWSTR(path.as_ptr()),
+            ..Default::default()
+        };
+        let mut data = WINTRUST_DATA {
+            cbStruct: std::mem::size_of::<WINTRUST_DATA>() as u32,
+            dwUIChoice: WTD_UI_NONE,
+            // Revocation needs the network, which the exam may not allow
+            fdwRevocationChecks: WTD_REVOKE_NONE,
+            dwUnionChoice: WTD_CHOICE_FILE,
+            Anonymous: WINTRUST_DATA_0 { pFile: &mut file },
+            dwStateAction: WTD_STATEACTION_VERIFY,
+            ..Default::default()
+        };
+        let mut action = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+        let status = unsafe {
+            let status = WinVerifyTrust(HWND::default(), &mut action, &mut data as *mut _ as _);
+            data.dwStateAction = WTD_STATEACTION_CLOSE;
+            WinVerifyTrust(HWND::default(), &mut action, &mut data as *mut _ as _);
+            status
+        };
+        match status {
+            0 => Some(true),
+            status if status == TRUST_E_NOSIGNATURE.0 => None,
+            status => {
+                log::warn!("Authenticode check failed: {status:#010x}");
+                Some(false)
+            }
+        }
+    }
+
+    /// True inside a Remote Desktop (RDP) session
+    pub fn is_remote_session() -> bool {
+        unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
+    }
+
+    /// Show a blocking, topmost error dialog
+    pub fn show_error(title: &str, message: &str) {
+        unsafe {
+            MessageBoxW(
+                None,
+                &HSTRING::from(message),
+                &HSTRING::from(title),
+                MB_OK | MB_ICONERROR | MB_TOPMOST | MB_SETFOREGROUND,
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::SecurityConfig;
+
+        #[test]
+        fn keyboard_hook_reinstalls_after_uninstall() {
+            // Leave the student's accessibility settings alone
+            let keyboard = KeyboardConfig {
+                block_accessibility_shortcuts: false,
+                ..Default::default()
+            };
+
+            assert!(install_keyboard_hook(&keyboard));
+            assert!(is_hook_active());
+            assert_ne!(HOOK_THREAD_ID.load(Ordering::SeqCst), 0);
+
+            assert!(uninstall_keyboard_hook());
+            assert!(!is_hook_active());
+            assert_eq!(HOOK_THREAD_ID.load(Ordering::SeqCst), 0);
+
+            // A second install must start a new hook thread, not return early
+            // on a stale HOOK_ACTIVE
+            assert!(install_keyboard_hook(&keyboard));
+            assert!(is_hook_active());
+            assert_ne!(HOOK_THREAD_ID.load(Ordering::SeqCst), 0);
+
+            assert!(uninstall_keyboard_hook());
+        }
+
+        fn combos(combos: &[KeyCombo]) -> Vec<BlockedCombo> {
+            combos.iter().filter_map(BlockedCombo::resolve).collect()
+        }
+
+        const NO_MODIFIERS: HeldModifiers = HeldModifiers {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            win: false,
+        };
+
+        const CTRL: HeldModifiers = HeldModifiers {
+            ctrl: true,
+            ..NO_MODIFIERS
+        };
+
+        #[test]
+        fn warn_combo_passes_through_and_is_reported() {
+            let combos = combos(&[KeyCombo {
+                modifiers: vec![KeyModifier::Ctrl],
+                key: "F".into(),
+                action: KeyAction::Warn,
+            }]);
+            let vk_f = 0x46;
+
+            let verdict = key_verdict(&combos, &[], false, vk_f, None, &CTRL);
+            // Anything but Block reaches CallNextHookEx
+            assert_eq!(verdict, KeyVerdict::Warn);
+            let reported = verdict
+                .report(vk_f, CTRL)
+                .expect("a warn combo is reported");
+            assert!(!reported.suppressed);
+            assert_eq!(reported.combo_name(), "Ctrl+F");
+
+            // Without its modifier the combo doesn't match at all
+            let plain = key_verdict(&combos, &[], false, vk_f, None, &NO_MODIFIERS);
+            assert_eq!(plain, KeyVerdict::Pass);
+            assert!(plain.report(vk_f, NO_MODIFIERS).is_none());
+        }
+
+        #[test]
+        fn allowed_key_passes_even_when_blocked() {
+            let combos = combos(&[KeyCombo::new(&[], "F7"), KeyCombo::new(&[], "F8")]);
+            let allowed = [vk_from_name("F7").unwrap()];
+            let verdict = |key, held| {
+                key_verdict(
+                    &combos,
+                    &allowed,
+                    false,
+                    vk_from_name(key).unwrap(),
+                    None,
+                    &held,
+                )
+            };
+
+            assert_eq!(verdict("F7", NO_MODIFIERS), KeyVerdict::Pass);
+            assert_eq!(verdict("F8", NO_MODIFIERS), KeyVerdict::Block);
+            // The exemption is for the bare key only
+            assert_eq!(verdict("F7", CTRL), KeyVerdict::Block);
+        }
+
+        #[test]
+        fn termination_is_limited_to_the_blocklist() {
+            let security = SecurityConfig::default();
+            let blocked = |name: &str| security.is_blocked(name);
+
+            assert_eq!(
+                termination_allowed(42, Some("OBS64.EXE".into()), blocked).unwrap(),
+                "OBS64.EXE"
+            );
+            assert_eq!(
+                termination_allowed(42, Some("notepad.exe".into()), blocked).unwrap_err(),
+                "notepad.exe (42) is not on the blocklist"
+            );
+            assert!(termination_allowed(42, None, blocked).is_err());
+
+            // The test runner itself isn't on the blocklist, so it survives
+            assert!(terminate_process_if(std::process::id(), blocked)
+                .unwrap_err()
+                .contains("is not on the blocklist"));
+        }
+
+        /// Keys AltGr types a character with, standing in for ToUnicodeEx
+        /// on each layout: @ and € on German (Q, E) and French (0, E)
+        const GERMAN: Option<&[&str]> = Some(&["Q", "E"]);
+        const FRENCH: Option<&[&str]> = Some(&["0", "E"]);
+        /// No AltGr: right Alt is a plain Alt
+        const US: Option<&[&str]> = None;
+
+        /// Press right Alt (AltGr where the layout has it) and then `key`,
+        /// as the hook sees it on `layout`
+        fn right_alt_verdict(layout: Option<&[&str]>, key: &str, right_ctrl: bool) -> KeyVerdict {
+            const TIME: u32 = 1000;
+            // AltGr layouts send a fake left Ctrl with the same event time
+            // just before right Alt
+            let lctrl_tick = if layout.is_some() { TIME } else { 0 };
+            let altgr = altgr_pressed(false, true, lctrl_tick, TIME);
+            let held = HeldModifiers {
+                ctrl: altgr || right_ctrl,
+                alt: true,
+                ..NO_MODIFIERS
+            };
+            let held = altgr_modifiers(held, altgr, right_ctrl, || {
+                layout.unwrap_or_default().contains(&key)
+            });
+            let combos = combos(&KeyboardConfig::default().blocked_combos);
+            key_verdict(&combos, &[], false, vk_from_name(key).unwrap(), None, &held)
+        }
+
+        fn ctrl_verdict(key: &str) -> KeyVerdict {
+            let combos = combos(&KeyboardConfig::default().blocked_combos);
+            key_verdict(&combos, &[], false, vk_from_name(key).unwrap(), None, &CTRL)
+        }
+
+        #[test]
+        fn us_right_alt_is_a_plain_alt() {
+            assert!(!altgr_pressed(false, true, 0, 1000));
+            assert_eq!(right_alt_verdict(US, "Q", false), KeyVerdict::Pass);
+            assert_eq!(right_alt_verdict(US, "F4", false), KeyVerdict::Block);
+            assert_eq!(ctrl_verdict("C"), KeyVerdict::Block);
+        }
+
+        #[test]
+        fn german_altgr_types_characters_but_not_shortcuts() {
+            assert_eq!(right_alt_verdict(GERMAN, "Q", false), KeyVerdict::Pass);
+            assert_eq!(right_alt_verdict(GERMAN, "E", false), KeyVerdict::Pass);
+            // Types nothing, so it is still Ctrl+C
+            assert_eq!(right_alt_verdict(GERMAN, "C", false), KeyVerdict::Block);
+            assert_eq!(ctrl_verdict("V"), KeyVerdict::Block);
+        }
+
+        #[test]
+        fn french_altgr_types_characters_but_not_shortcuts() {
+            assert_eq!(right_alt_verdict(FRENCH, "0", false), KeyVerdict::Pass);
+            assert_eq!(right_alt_verdict(FRENCH, "E", false), KeyVerdict::Pass);
+            assert_eq!(right_alt_verdict(FRENCH, "P", false), KeyVerdict::Block);
+            assert_eq!(right_alt_verdict(FRENCH, "F4", false), KeyVerdict::Block);
+        }
+
+        #[test]
+        fn right_ctrl_with_altgr_is_a_chord() {
+            // E types €, but with right Ctrl also held Ctrl+Alt+E is kept as
+            // is; Ctrl+Alt+C is then blocked as Ctrl+C
+            let held = HeldModifiers {
+                ctrl: true,
+                alt: true,
+                ..NO_MODIFIERS
+            };
+            let kept = altgr_modifiers(held, true, true, || true);
+            assert!(kept.ctrl && kept.alt);
+            assert_eq!(right_alt_verdict(GERMAN, "C", true), KeyVerdict::Block);
+        }
+
+        #[test]
+        fn altgr_stays_down_through_repeats_until_released() {
+            assert!(altgr_pressed(false, true, 1000, 1000));
+            // A repeat without its fake Ctrl
+            assert!(altgr_pressed(true, true, 1000, 1030));
+            assert!(!altgr_pressed(true, false, 1000, 1060));
+        }
+    }
+}
+
+// ============================================================================
+// macOS Security Module - Kiosk mode and screenshot protection
+// ============================================================================
+
+#[cfg(target_os = "macos")]
+mod macos_security {
+    use objc2::MainThreadMarker;
+    use objc2_app_kit::{
+        NSApplication, NSApplicationPresentationOptions, NSWindow, NSWindowSharingType,
+    };
+    use objc2_core_foundation::{
+        kCFRunLoopCommonModes, CFMachPort, CFRetained, CFRunLoop, CFString,
+    };
+    use objc2_core_graphics::{
+        CGDirectDisplayID, CGDisplayBounds, CGError, CGEvent, CGEventField, CGEventFlags,
+        CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement, CGEventTapProxy, CGEventType,
+        CGGetActiveDisplayList,
+    };
+    use std::cell::OnceCell;
+    use std::ffi::c_void;
+    use std::ptr::{self, NonNull};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    static TAP_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    thread_local! {
+        /// The tap's mach port, owned by the tap thread so the callback can
+        /// re-enable it when macOS disables a tap it considers too slow.
+        static EVENT_TAP: OnceCell<CFRetained<CFMachPort>> = const { OnceCell::new() };
+    }
+
+    // Virtual key codes (kVK_*)
+    const KVK_H: i64 = 0x04;
+    const KVK_Q: i64 = 0x0C;
+    const KVK_3: i64 = 0x14;
+    const KVK_4: i64 = 0x15;
+    const KVK_5: i64 = 0x17;
+    const KVK_TAB: i64 = 0x30;
+    const KVK_SPACE: i64 = 0x31;
+
+    /// Cmd+Tab, Cmd+Q, Cmd+Space (Spotlight), Cmd+H and Cmd+Shift+3/4/5
+    /// (screenshots). Cmd+Option+Esc stays available as OS-level recovery.
+    fn is_blocked(keycode: i64, flags: CGEventFlags) -> bool {
+        if !flags.contains(CGEventFlags::MaskCommand) {
+            return false;
+        }
+        match keycode {
+            KVK_TAB | KVK_Q | KVK_SPACE | KVK_H => true,
+            KVK_3 | KVK_4 | KVK_5 => flags.contains(CGEventFlags::MaskShift),
+            _ => false,
+        }
+    }
+
+    unsafe extern "C-unwind" fn event_tap_callback(
+        _proxy: CGEventTapProxy,
+        event_type: CGEventType,
+        event: NonNull<CGEvent>,
+        _user_info: *mut c_void,
+    ) -> *mut CGEvent {
+        if event_type == CGEventType::TapDisabledByTimeout
+            || event_type == CGEventType::TapDisabledByUserInput
+        {
+            EVENT_TAP.with(|tap| {
+                if let Some(tap) = tap.get() {
+                    CGEvent::tap_enable(tap, true);
+                }
+            });
+            return event.as_ptr();
+        }
+
+        if event_type == CGEventType::KeyDown {
+            let event_ref = Some(event.as_ref());
+            let keycode =
+                CGEvent::integer_value_field(event_ref, CGEventField::KeyboardEventKeycode);
+            if is_blocked(keycode, CGEvent::flags(event_ref)) {
+                // Returning NULL deletes the event
+                return ptr::null_mut();
+            }
+        }
+        event.as_ptr()
+    }
+
+    /// Install a session-level CGEventTap that suppresses the combinations in
+    /// `is_blocked`. macOS only creates the tap once the app has been granted
+    /// Accessibility access; without it this logs and the app continues with
+    /// kiosk presentation options as the only protection.
+    pub fn install_keyboard_hook() {
+        if TAP_ACTIVE.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        thread::spawn(|| unsafe {
+            let tap = CGEvent::tap_create(
+                CGEventTapLocation::SessionEventTap,
+                CGEventTapPlacement::HeadInsertEventTap,
+                CGEventTapOptions::Default,
+                1 << CGEventType::KeyDown.0,
+                Some(event_tap_callback),
+                ptr::null_mut(),
+            );
+            let Some(tap) = tap else {
+                log::warn!("keyboard tap unavailable: Accessibility access not granted");
+                TAP_ACTIVE.store(false, Ordering::SeqCst);
+                return;
+            };
+            let (Some(source), Some(run_loop)) = (
+                CFMachPort::new_run_loop_source(None, Some(&tap), 0),
+                CFRunLoop::current(),
+            ) else {
+                log::warn!("keyboard tap could not be scheduled");
+                TAP_ACTIVE.store(false, Ordering::SeqCst);
+                return;
+            };
+
+            run_loop.add_source(Some(&source), kCFRunLoopCommonModes);
+            CGEvent::tap_enable(&tap, true);
+            EVENT_TAP.with(|cell| {
+                let _ = cell.set(tap);
+            });
+
+            // Run loop to keep the tap alive
+            CFRunLoop::run();
+            TAP_ACTIVE.store(false, Ordering::SeqCst);
+        });
+    }
+
+    pub fn is_hook_active() -> bool {
+        TAP_ACTIVE.load(Ordering::SeqCst)
+    }
+
+    /// Origin of each active display, as reported by CGGetActiveDisplayList
+    /// and CGDisplayBounds. Software-mirrored displays are active and share
+    /// their origin; hardware mirrors aren't listed at all.
+    #[allow(unused_unsafe)]
+    pub fn active_display_origins() -> Option<Vec<(i32, i32)>> {
+        let mut count = 0u32;
+        let error = unsafe { CGGetActiveDisplayList(0, ptr::null_mut(), &mut count) };
+        if error != CGError::Success {
+            return None;
+        }
+        let mut ids: Vec<CGDirectDisplayID> = vec![0; count as usize];
+        let error = unsafe { CGGetActiveDisplayList(count, ids.as_mut_ptr(), &mut count) };
+        if error != CGError::Success {
+            return None;
+        }
+        ids.truncate(count as usize);
+        Some(
+            ids.into_iter()
+                .map(|id| {
+                    let origin = unsafe { CGDisplayBounds(id) }.origin;
+                    (origin.x as i32, origin.y as i32)
+                })
+                .collect(),
+        )
+    }
+
+    /// Put the app into kiosk mode: hides the Dock and menu bar and disables
+    /// Cmd+Tab process switching and hiding the app. Force-quit and session
+    /// termination intentionally remain available as OS-level recovery paths.
+    /// Must be called on the main thread.
+    #[allow(unused_unsafe)]
+    pub fn enable_kiosk_mode() {
+        let Some(mtm) = MainThreadMarker::new() else {
+            log::warn!("kiosk mode skipped: not on main thread");
+            return;
+        };
+        let app = NSApplication::sharedApplication(mtm);
+        unsafe {
+            app.setPresentationOptions(
+                NSApplicationPresentationOptions::HideDock
+                    | NSApplicationPresentationOptions::HideMenuBar
+                    | NSApplicationPresentationOptions::DisableProcessSwitching
+                    | NSApplicationPresentationOptions::DisableHideApplication,
+            );
+        }
+    }
+
+    /// Exclude the window from screenshots and screen recordings
+    /// (Cmd+Shift+3/4/5 capture a blank area where the window is).
+    #[allow(unused_unsafe)]
+    pub fn disable_window_capture(window: &tauri::WebviewWindow) {
+        if let Ok(ptr) = window.ns_window() {
+            unsafe {
+                let ns_window: &NSWindow = &*ptr.cast();
+                ns_window.setSharingType(NSWindowSharingType::None);
+            }
+        }
+    }
+
+    #[link(name = "Security", kind = "framework")]
+    extern "C" {
+        fn SecCodeCopySelf(flags: u32, code: *mut *const c_void) -> i32;
+        fn SecRequirementCreateWithString(
+            text: *const c_void,
+            flags: u32,
+            requirement: *mut *const c_void,
+        ) -> i32;
+        fn SecCodeCheckValidity(code: *const c_void, flags: u32, requirement: *const c_void)
+            -> i32;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *const c_void);
+    }
+
+    const ERR_SEC_CS_UNSIGNED: i32 = -67062;
+    const ERR_SEC_CS_REQ_FAILED: i32 = -67050;
+
+    /// Signed with a Developer ID (or App Store) certificate
+    const RELEASE_REQUIREMENT: &str = "anchor apple generic";
+
+    /// Whether this process's code signature is intact and from a Developer
+    /// ID certificate, or `None` when it's unsigned or only ad-hoc signed (as
+    /// the linker does for Apple Silicon builds), which anyone can redo
+    pub fn code_signature_valid() -> Option<bool> {
+        let text = CFString::from_str(RELEASE_REQUIREMENT);
+        let mut code = ptr::null();
+        let mut requirement = ptr::null();
+        let status = unsafe {
+            let mut status = SecCodeCopySelf(0, &mut code);
+            if status == 0 {
+                status = SecRequirementCreateWithString(
+                    (&*text as *const CFString).cast(),
+                    0,
+                    &mut requirement,
+                );
+            }
+            if status == 0 {
+                status = SecCodeCheckValidity(code, 0, requirement);
+            }
+            for object in [code, requirement] {
+                if !object.is_null() {
+                    CFRelease(object);
+                }
+            }
+            status
+        };
+        match status {
+            0 => Some(true),
+            ERR_SEC_CS_UNSIGNED | ERR_SEC_CS_REQ_FAILED => None,
+            status => {
+                log::warn!("code signature check failed: {status}");
+                Some(false)
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Linux Security Module - X11 key grabs and RandR monitor count
+// ============================================================================
+
+/// Linux support is X11 only. Every block here is best effort: when a grab is
+/// refused the app logs it and keeps running with whatever did engage.
+///
+/// - Super, Alt+Tab and PrintScreen are passive `GrabKey`s on the root window.
+///   They need no privileges, but fail with BadAccess when the window manager
+///   already holds the same grab (common for Alt+Tab and PrintScreen).
+/// - Ctrl+Alt+Fn VT switches are grabbed too, but the X server performs the
+///   switch before clients see the key on most setups. Reliably preventing it
+///   needs `Option "DontVTSwitch"` in xorg.conf or an evdev `EVIOCGRAB`, both
+///   of which require root and are left to the system image.
+/// - Wayland sessions without XWayland access to the root window get no key
+///   blocking at all.
+#[cfg(target_os = "linux")]
+mod linux_security {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::OnceLock;
+    use std::thread;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::randr::ConnectionExt as _;
+    use x11rb::protocol::xproto::{ConnectionExt as _, GrabMode, Keycode, ModMask, Window};
+    use x11rb::rust_connection::RustConnection;
+
+    static GRABS_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    // Keysyms (X11/keysymdef.h)
+    const XK_TAB: u32 = 0xFF09;
+    const XK_PRINT: u32 = 0xFF61;
+    const XK_F1: u32 = 0xFFBE;
+    const XK_SUPER_L: u32 = 0xFFEB;
+    const XK_SUPER_R: u32 = 0xFFEC;
+
+    /// Keysyms to grab and the modifiers that must accompany them
+    fn blocked_keys() -> Vec<(u32, ModMask)> {
+        let ctrl_alt = ModMask::CONTROL | ModMask::M1;
+        let mut keys = vec![
+            (XK_SUPER_L, ModMask::ANY),
+            (XK_SUPER_R, ModMask::ANY),
+            (XK_PRINT, ModMask::ANY),
+            (XK_TAB, ModMask::M1),
+        ];
+        keys.extend((0..12).map(|n| (XK_F1 + n, ctrl_alt)));
+        keys
+    }
+
+    /// Every keycode the current keyboard mapping assigns to `keysym`
+    fn keycodes_for(conn: &RustConnection, keysym: u32) -> Vec<Keycode> {
+        let setup = conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let Ok(mapping) = conn
+            .get_keyboard_mapping(min, max - min + 1)
+            .map_err(x11rb::errors::ReplyError::from)
+            .and_then(|cookie| cookie.reply())
+        else {
+            return Vec::new();
+        };
+
+        let per_keycode = usize::from(mapping.keysyms_per_keycode.max(1));
+        mapping
+            .keysyms
+            .chunks(per_keycode)
+            .zip(min..=max)
+            .filter(|(syms, _)| syms.contains(&keysym))
+            .map(|(_, keycode)| keycode)
+            .collect()
+    }
+
+    /// Grab `keycode` with `modifiers` on `root`, also covering the CapsLock
+    /// and NumLock variants so those toggles can't slip a combo past us.
+    fn grab(conn: &RustConnection, root: Window, keycode: Keycode, modifiers: ModMask) -> bool {
+        let variants = if modifiers == ModMask::ANY {
+            vec![ModMask::ANY]
+        } else {
+            [
+                ModMask::default(),
+                ModMask::LOCK,
+                ModMask::M2,
+                ModMask::LOCK | ModMask::M2,
+            ]
+            .into_iter()
+            .map(|toggles| modifiers | toggles)
+            .collect()
+        };
+
+        variants.into_iter().all(|mask| {
+            conn.grab_key(true, root, mask, keycode, GrabMode::ASYNC, GrabMode::ASYNC)
+                .map_err(x11rb::errors::ReplyError::from)
+                .and_then(|cookie| cookie.check())
+                .is_ok()
+        })
+    }
+
+    /// Grab the blocked keys on the root window. Grabbed key events are
+    /// delivered to this thread's connection and discarded, so the window
+    /// manager and focused client never see them.
+    pub fn install_keyboard_hook() {
+        if GRABS_ACTIVE.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        thread::spawn(|| {
+            let Ok((conn, screen_num)) = x11rb::connect(None) else {
+                log::warn!("keyboard grabs skipped: no X11 display");
+                GRABS_ACTIVE.store(false, Ordering::SeqCst);
+                return;
+            };
+            let root = conn.setup().roots[screen_num].root;
+
+            for (keysym, modifiers) in blocked_keys() {
+                for keycode in keycodes_for(&conn, keysym) {
+                    if !grab(&conn, root, keycode, modifiers) {
+                        log::warn!(
+                            "could not grab keysym 0x{keysym:04X}; \
+                             it is likely held by the window manager"
+                        );
+                    }
+                }
+            }
+            let _ = conn.flush();
+
+            // Event loop to keep the grabs alive
+            while conn.wait_for_event().is_ok() {}
+            GRABS_ACTIVE.store(false, Ordering::SeqCst);
+        });
+    }
+
+    pub fn is_hook_active() -> bool {
+        GRABS_ACTIVE.load(Ordering::SeqCst)
+    }
+
+    /// Connection reused by `active_display_origins`, opened on first use
+    static DISPLAY_CONNECTION: OnceLock<Option<(RustConnection, Window)>> = OnceLock::new();
+
+    /// Origin of each active monitor reported by RandR 1.5. Outputs
+    /// mirroring each other are already a single monitor here.
+    pub fn active_display_origins() -> Option<Vec<(i32, i32)>> {
+        let (conn, root) = DISPLAY_CONNECTION
+            .get_or_init(|| {
+                let (conn, screen_num) = x11rb::connect(None).ok()?;
+                let root = conn.setup().roots[screen_num].root;
+                Some((conn, root))
+            })
+            .as_ref()?;
+
+        let monitors = conn.randr_get_monitors(*root, true).ok()?.reply().ok()?;
+        Some(
+            monitors
+                .monitors
+                .iter()
+                .map(|monitor| (i32::from(monitor.x), i32::from(monitor.y)))
+                .collect(),
+        )
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// When setup began, for `window.startup_grace_secs`
+static SETUP_AT: OnceLock<Instant> = OnceLock::new();
+
+/// Longest exit reason recorded in the violation log
+const MAX_EXIT_REASON_LEN: usize = 200;
+
+#[derive(Serialize)]
+struct ExitDetails<'a> {
+    reason: &'a str,
+}
+
+/// Set by `exit_lockdown` right before it closes the main window, so the
+/// `CloseRequested` handler lets that one close through. Anything else that
+/// asks to close the window (Alt+F4, the taskbar) still finds it false.
+static CLOSE_ALLOWED: AtomicBool = AtomicBool::new(false);
+
+/// Record why the app is exiting, release the native lockdown hooks, then
+/// close the main window and exit. Every intentional exit path goes through
+/// here so the keyboard hook never outlives the session and the log tells a
+/// normal submission apart from an emergency exit; a killed process leaves no
+/// `exit` entry at all.
+fn exit_lockdown(app: &AppHandle, reason: &str) {
+    end_session(app, reason);
+    mark_clean_exit();
+
+    if let Some(window) = app.get_webview_window("main") {
+        CLOSE_ALLOWED.store(true, Ordering::SeqCst);
+        if let Err(error) = window.close() {
+            CLOSE_ALLOWED.store(false, Ordering::SeqCst);
+            log::warn!("failed to close the main window: {error}");
+        }
+    }
+    app.exit(0);
+}
+
+/// Everything an intentional exit does before the process goes away: record
+/// the summary, drop the recovery marker and browser data, flush the
+/// violation log and release the keyboard hook
+fn end_session(app: &AppHandle, reason: &str) {
+    log::info!("exiting: {reason}");
+    let summary = SessionSummary::now();
+    log_event("session-summary", &summary);
+    let _ = app.emit_to("main", "session-summary", summary);
+    log_event("exit", &ExitDetails { reason });
+    clear_recovery_marker();
+    clear_view_state(app);
+    if CLEAR_BROWSER_DATA_ON_EXIT.load(Ordering::SeqCst) {
+        if let Err(error) = clear_browser_data(app.clone()) {
+            log::warn!("failed to clear browser data: {error}");
+        }
+    }
+    if let Some(log) = VIOLATION_LOG.get() {
+        if let Err(error) = log.flush() {
+            log::error!("failed to flush violation log: {error}");
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        stop_process_watcher();
+        if !windows_security::uninstall_keyboard_hook() {
+            log::error!("keyboard hook did not stop before exit");
+        }
+        windows_security::restore_accessibility_shortcuts();
+        windows_security::restore_touch_keyboard();
+    }
+}
+
+/// Word the emergency exit confirmation window asks for
+const EXIT_CONFIRMATION_WORD: &str = "EXIT";
+
+/// Environment variable that skips `debug_settings.exit_confirmation`, for
+/// automated tests that drive the emergency exit
+const INSTANT_EXIT_ENV: &str = "LOCKDOWN_INSTANT_EXIT";
+
+/// Label of the window `open_exit_confirmation` creates
+const EXIT_CONFIRMATION_LABEL: &str = "exit-confirmation";
+
+/// Run the emergency exit, or with `confirm` (and no `INSTANT_EXIT_ENV`)
+/// show a small window asking for `EXIT_CONFIRMATION_WORD` first, so a
+/// tester brushing the shortcut doesn't lose their session
+fn emergency_exit(app: &AppHandle, confirm: bool) {
+    if !confirm || std::env::var_os(INSTANT_EXIT_ENV).is_some() {
+        log::info!("emergency exit triggered");
+        exit_lockdown(app, "emergency-exit");
+        return;
+    }
+    if let Some(window) = app.get_webview_window(EXIT_CONFIRMATION_LABEL) {
+        let _ = window.set_focus();
+        return;
+    }
+
+    log::info!("emergency exit requested; asking for confirmation");
+    // Built off the event loop thread, which the shortcut callback runs on
+    let app = app.clone();
+    thread::spawn(move || {
+        let built = WebviewWindowBuilder::new(
+            &app,
+            EXIT_CONFIRMATION_LABEL,
+            WebviewUrl::App("exit-confirm.html".into()),
+        )
+        .devtools(cfg!(debug_assertions))
+        .title("Confirm emergency exit")
+        .inner_size(380.0, 240.0)
+        .center()
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .decorations(false)
+        .resizable(false)
+        .maximizable(false)
+        .minimizable(false)
+        .focused(true)
+        .build();
+        if let Err(error) = built {
+            log::error!("failed to open the exit confirmation: {error}");
+        }
+    });
+}
+
+/// Exit if `word` is `EXIT_CONFIRMATION_WORD`, from the confirmation window
+#[tauri::command]
+fn confirm_emergency_exit(app: AppHandle, word: String) -> Result<(), String> {
+    if !word.trim().eq_ignore_ascii_case(EXIT_CONFIRMATION_WORD) {
+        return Err(format!("Type {EXIT_CONFIRMATION_WORD} to exit."));
+    }
+    log::info!("emergency exit confirmed");
+    exit_lockdown(&app, "emergency-exit");
+    Ok(())
+}
+
+/// Close the confirmation window and carry on with the session
+#[tauri::command]
+fn cancel_emergency_exit(app: AppHandle) {
+    log::info!("emergency exit cancelled");
+    if let Some(window) = app.get_webview_window(EXIT_CONFIRMATION_LABEL) {
+        let _ = window.close();
+    }
+}
+
+/// Exit with `reason` (e.g. "submitted") recorded in the violation log.
+/// Rejected while the keyboard lockdown is switched off, so a page that turns
+/// it off for the instructions can't submit without having restored it.
+#[tauri::command]
+fn submit_and_exit(app: AppHandle, reason: String) -> Result<(), String> {
+    if !KEYBOARD_LOCKDOWN.load(Ordering::SeqCst) {
+        return Err("re-enable the keyboard lockdown before submitting".into());
+    }
+    let reason: String = reason.chars().take(MAX_EXIT_REASON_LEN).collect();
+    exit_lockdown(&app, &reason);
+    Ok(())
+}
+
+#[tauri::command]
+fn close_app(app: AppHandle) -> Result<(), String> {
+    submit_and_exit(app, "unspecified".into())
+}
+
+#[tauri::command]
+fn close_lockdown(app: AppHandle) {
+    exit_lockdown(&app, "close-lockdown");
+}
+
+#[tauri::command]
+fn close_during_loading(
+    app: AppHandle,
+    state: State<'_, Arc<QuizSessionState>>,
+) -> Result<(), String> {
+    match *state.phase.lock().unwrap() {
+        QuizPhase::Loading | QuizPhase::CheckingUpdate { .. } => {}
+        QuizPhase::Ready => {
+            return Err("the quiz is active; use the quiz close flow".into());
+        }
+        QuizPhase::Updating { .. } => {
+            return Err("an application update is being installed".into());
+        }
+    }
+
+    exit_lockdown(&app, "closed-during-loading");
+    Ok(())
+}
+
+#[tauri::command]
+fn mark_quiz_ready(app: AppHandle, state: State<'_, Arc<QuizSessionState>>) -> Result<(), String> {
+    state.exam_active.store(true, Ordering::SeqCst);
+    let close_recovery = {
+        let mut phase = state.phase.lock().unwrap();
+        match &mut *phase {
+            QuizPhase::CheckingUpdate { ready_requested }
+            | QuizPhase::Updating { ready_requested } => {
+                *ready_requested = true;
+                false
+            }
+            QuizPhase::Loading => {
+                *phase = QuizPhase::Ready;
+                true
+            }
+            QuizPhase::Ready => true,
+        }
+    };
+
+    if close_recovery {
+        close_loading_recovery(&app)?;
+    }
+    Ok(())
+}
+
+/// Whether the keyboard lockdown is meant to be on. Starts on; only
+/// `set_keyboard_lockdown` turns it off.
+static KEYBOARD_LOCKDOWN: AtomicBool = AtomicBool::new(true);
+
+#[derive(Clone, Serialize)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct LockdownState {
+    keyboard: bool,
+}
+
+/// Switch the keyboard hook off and back on at runtime, e.g. so students can
+/// copy a case ID or tab between fields on the instructions page. Switching
+/// it off needs the exit password (returns false for a wrong one, which
+/// counts towards the `attempt_exit` lockout); switching it back on needs
+/// none.
+#[tauri::command]
+fn set_keyboard_lockdown(
+    app: AppHandle,
+    security: State<'_, SecurityConfig>,
+    enabled: bool,
+    password: Option<String>,
+) -> Result<bool, String> {
+    if !enabled && !check_exit_password(&security, password.as_deref().unwrap_or_default())? {
+        return Ok(false);
+    }
+    apply_keyboard_lockdown(&app, enabled)?;
+    Ok(true)
+}
+
+/// Turn the keyboard hook on or off without any check, for
+/// `set_keyboard_lockdown` and `set_lockdown_stage`. Emits `lockdown-state`
+/// whenever the setting changes.
+fn apply_keyboard_lockdown(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        if KEYBOARD_LOCKDOWN.swap(enabled, Ordering::SeqCst) == enabled {
+            return Ok(());
+        }
+
+        if enabled {
+            if !windows_security::reinstall_keyboard_hook() {
+                return Err("keyboard hook failed to install".into());
+            }
+        } else if !windows_security::uninstall_keyboard_hook() {
+            KEYBOARD_LOCKDOWN.store(true, Ordering::SeqCst);
+            return Err("keyboard hook did not stop".into());
+        }
+
+        log::info!(
+            "keyboard lockdown {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+        emit_violation(app, "lockdown-state", LockdownState { keyboard: enabled });
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = (app, enabled);
+        Err("the keyboard lockdown can only be toggled on Windows".into())
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[derive(Clone, Serialize)]
+struct HookReinstalledPayload {
+    reinstalled: bool,
+}
+
+/// Re-run the hook self-test every `interval` and reinstall the hook when it
+/// fails. Windows silently removes a low-level hook whose callback overruns
+/// LowLevelHooksTimeout (heavy load, antivirus scanning the process) and
+/// never tells the app, so the self-test is the only way to notice. Emits
+/// `hook-reinstalled` after each attempt, but only the first of a run of
+/// failed ones, so a hook that can't come back doesn't flood the page.
+#[cfg(target_os = "windows")]
+fn watch_keyboard_hook(app: AppHandle, interval: Duration) {
+    let mut failing = false;
+    loop {
+        thread::sleep(interval);
+        if !KEYBOARD_LOCKDOWN.load(Ordering::SeqCst) || windows_security::run_self_test() {
+            failing = false;
+            continue;
+        }
+
+        log::warn!("keyboard hook stopped suppressing keys; reinstalling");
+        if !windows_security::uninstall_keyboard_hook() {
+            log::error!("evicted keyboard hook thread did not stop");
+        }
+        // set_keyboard_lockdown may have switched it off in the meantime
+        if !KEYBOARD_LOCKDOWN.load(Ordering::SeqCst) {
+            continue;
+        }
+        let reinstalled =
+            windows_security::reinstall_keyboard_hook() && windows_security::run_self_test();
+        if reinstalled || !failing {
+            emit_violation(
+                &app,
+                "hook-reinstalled",
+                HookReinstalledPayload { reinstalled },
+            );
+        }
+        failing = !reinstalled;
+    }
+}
+
+/// Let Ctrl+V through the keyboard hook and page script while the page has
+/// an input or textarea focused. The frontend toggles this on focusin/focusout.
+#[tauri::command]
+fn set_paste_allowed(app: AppHandle, allowed: bool) {
+    #[cfg(target_os = "windows")]
+    windows_security::set_paste_allowed(allowed);
+
+    if let Some(win) = app.get_webview_window("main") {
+        let _ = win.eval(format!(
+            "window.__UNDERSTANDLY_LOCKDOWN_PASTE_ALLOWED__ = {allowed};"
+        ));
+    }
+}
+
+/// `stages` from the config, managed for `set_lockdown_stage`
+struct LockdownStages(BTreeMap<String, LockdownStage>);
+
+#[derive(Clone, Serialize)]
+struct StageChangedPayload {
+    stage: String,
+    settings: LockdownStage,
+}
+
+/// Switch to one of the configured `stages`, e.g. `exam` when the student
+/// starts and `review` once they submit, instead of toggling the keyboard
+/// hook, paste, always-on-top and monitor enforcement one by one. Emits
+/// `stage-changed`. A stage that relaxes any protection (keyboard hook or
+/// always-on-top off, paste allowed, monitor enforcement off) needs the exit
+/// password and returns false for a wrong one, which counts towards the
+/// `attempt_exit` lockout; a fully locked stage needs none.
+#[tauri::command]
+fn set_lockdown_stage(
+    app: AppHandle,
+    security: State<'_, SecurityConfig>,
+    stages: State<'_, LockdownStages>,
+    stage: String,
+    password: Option<String>,
+) -> Result<bool, String> {
+    let settings = stages
+        .0
+        .get(&stage)
+        .cloned()
+        .ok_or_else(|| format!("unknown lockdown stage {stage:?}"))?;
+    if settings.relaxes_protection()
+        && !check_exit_password(&security, password.as_deref().unwrap_or_default())?
+    {
+        return Ok(false);
+    }
+
+    #[cfg(target_os = "windows")]
+    apply_keyboard_lockdown(&app, settings.keyboard_lockdown)?;
+    set_paste_allowed(app.clone(), settings.paste_allowed);
+    TOPMOST_RESTORE.lock().unwrap().take();
+    apply_always_on_top(&app, settings.always_on_top)?;
+    MONITOR_ENFORCEMENT.store(settings.monitor_enforcement, Ordering::SeqCst);
+
+    log::info!("lockdown stage: {stage}");
+    emit_violation(
+        &app,
+        "stage-changed",
+        StageChangedPayload { stage, settings },
+    );
+    Ok(true)
+}
+
+/// Change the accessibility settings live, e.g. from a proctor's console
+#[tauri::command]
+fn set_accessibility(app: AppHandle, high_contrast: bool, font_scale: f32) -> Result<(), String> {
+    if !FONT_SCALE_RANGE.contains(&font_scale) {
+        return Err(format!(
+            "font_scale must be between {} and {}",
+            FONT_SCALE_RANGE.start(),
+            FONT_SCALE_RANGE.end()
+        ));
+    }
+
+    let settings = serde_json::to_string(&AccessibilityConfig {
+        high_contrast,
+        font_scale,
+    })
+    .map_err(|error| error.to_string())?;
+    let win = app
+        .get_webview_window("main")
+        .ok_or("main window is not open")?;
+    win.eval(format!(
+        "window.__UNDERSTANDLY_LOCKDOWN_SET_ACCESSIBILITY__?.({settings});"
+    ))
+    .map_err(|error| error.to_string())
+}
+
+/// `watermark.template`, set when the watermark is enabled
+static WATERMARK_TEMPLATE: OnceLock<String> = OnceLock::new();
+
+/// The watermark text, with the template applied; empty until
+/// `set_watermark_text` is called
+static WATERMARK_TEXT: Mutex<String> = Mutex::new(String::new());
+
+/// Longest text `set_watermark_text` accepts
+const MAX_WATERMARK_LEN: usize = 100;
+
+/// Hand the current watermark text to the page in `window`, if there is one
+fn apply_watermark(window: &tauri::WebviewWindow) {
+    let text = WATERMARK_TEXT.lock().unwrap().clone();
+    if text.is_empty() {
+        return;
+    }
+    let text = serde_json::to_string(&text).expect("watermark text should serialize to JSON");
+    let _ = window.eval(format!(
+        "window.__UNDERSTANDLY_LOCKDOWN_SET_WATERMARK__?.({text});"
+    ));
+}
+
+/// Fill `{text}` in `watermark.template`, e.g. with the student's name and
+/// ID once they have logged in, and show the watermark. It stays on every
+/// page for the rest of the session.
+#[tauri::command]
+fn set_watermark_text(app: AppHandle, text: String) -> Result<(), String> {
+    let template = WATERMARK_TEMPLATE
+        .get()
+        .ok_or("the watermark is not enabled")?;
+    let text: String = text.chars().take(MAX_WATERMARK_LEN).collect();
+    *WATERMARK_TEXT.lock().unwrap() = template.replace("{text}", &text);
+    log_event("watermark-set", &serde_json::json!({ "text": text }));
+    let win = app
+        .get_webview_window("main")
+        .ok_or("main window is not open")?;
+    apply_watermark(&win);
+    Ok(())
+}
+
+/// Check if multiple monitors are connected (for the frontend to react)
+#[tauri::command]
+fn check_multiple_monitors(app: AppHandle) -> bool {
+    monitor_count(&app).is_some_and(|count| count > 1)
+}
+
+/// Get monitor count, with duplicated displays counted once
+#[tauri::command]
+fn get_monitor_count(app: AppHandle) -> usize {
+    monitor_count(&app).unwrap_or(1)
+}
+
+/// Both display counts, for a policy that also refuses duplicated displays
+#[tauri::command]
+fn get_monitor_counts(app: AppHandle) -> Option<MonitorCounts> {
+    monitor_counts(&app)
+}
+
+#[derive(Serialize)]
+struct DisplayBounds {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+#[derive(Serialize)]
+struct DisplayInfo {
+    index: usize,
+    /// Physical pixels
+    bounds: DisplayBounds,
+    primary: bool,
+    /// Physical pixels per logical pixel, e.g. 2.0 on a 4K laptop panel at
+    /// 200% next to 1.0 on a 1080p monitor
+    scale_factor: f64,
+    device_name: String,
+    /// `index` of an earlier display at the same origin, i.e. one this
+    /// display duplicates
+    mirror_of: Option<usize>,
+}
+
+/// Where each display is, so the page can say which one to unplug
+#[tauri::command]
+fn get_displays(app: AppHandle) -> Vec<DisplayInfo> {
+    let mut displays = displays(&app);
+    for index in 0..displays.len() {
+        let origin = (displays[index].bounds.left, displays[index].bounds.top);
+        displays[index].mirror_of = displays[..index]
+            .iter()
+            .find(|display| (display.bounds.left, display.bounds.top) == origin)
+            .map(|display| display.index);
+    }
+    displays
+}
+
+/// Top-left corner of the monitor `set_fullscreen_on_monitor` chose, where
+/// the quiz goes once the loading sidebar closes. The primary monitor when
+/// none was chosen.
+static QUIZ_MONITOR: Mutex<Option<tauri::PhysicalPosition<i32>>> = Mutex::new(None);
+
+/// Move the main window to the monitor at `index` (as in `available_monitors`,
+/// the same order `get_displays` uses) and make it fill that monitor, e.g.
+/// when it opened on the screen meant for an interpreter feed. Borderless
+/// maximized mode maximizes there instead of entering fullscreen. While the
+/// loading sidebar is open the move waits until it closes.
+#[tauri::command]
+async fn set_fullscreen_on_monitor(app: AppHandle, index: u32) -> Result<(), String> {
+    let monitors = app
+        .available_monitors()
+        .map_err(|error| error.to_string())?;
+    let monitor = monitors.get(index as usize).ok_or_else(|| {
+        format!(
+            "no monitor {index}; {} connected (numbered from 0)",
+            monitors.len()
+        )
+    })?;
+    let position = *monitor.position();
+    let covered = app.webview_windows().iter().any(|(label, window)| {
+        is_cover_window(label) && window.outer_position().ok() == Some(position)
+    });
+    if covered {
+        return Err(format!(
+            "monitor {index} is blanked by window.cover_secondary_monitors"
+        ));
+    }
+    let window = app
+        .get_webview_window("main")
+        .ok_or("main window not found")?;
+
+    *QUIZ_MONITOR.lock().unwrap() = Some(position);
+    log::info!("quiz pinned to monitor {index} at {position:?}");
+    if app.get_webview_window("loading-recovery").is_some() {
+        return Ok(());
+    }
+    move_to_quiz_monitor(&app, &window)
+}
+
+/// Put the main window on `QUIZ_MONITOR` in the configured window mode
+fn move_to_quiz_monitor(app: &AppHandle, window: &tauri::WebviewWindow) -> Result<(), String> {
+    let position = QUIZ_MONITOR.lock().unwrap().or_else(|| {
+        app.primary_monitor()
+            .ok()
+            .flatten()
+            .map(|monitor| *monitor.position())
+    });
+    // Fullscreen and maximized windows ignore moves, and take the monitor
+    // they're on when entering either
+    let _ = window.set_fullscreen(false);
+    let _ = window.unmaximize();
+    if let Some(position) = position {
+        let _ = window.set_position(position);
+    }
+    match WINDOW_MODE.get().copied().unwrap_or_default() {
+        WindowMode::Fullscreen => window.set_fullscreen(true),
+        WindowMode::BorderlessMaximized => window.maximize(),
+    }
+    .map_err(|error| error.to_string())?;
+    let _ = window.set_focus();
+    Ok(())
+}
+
+fn displays(app: &AppHandle) -> Vec<DisplayInfo> {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = app;
+        windows_security::displays()
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let primary = app.primary_monitor().ok().flatten();
+        app.available_monitors()
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(index, monitor)| {
+                let position = monitor.position();
+                let size = monitor.size();
+                DisplayInfo {
+                    index,
+                    bounds: DisplayBounds {
+                        left: position.x,
+                        top: position.y,
+                        right: position.x + size.width as i32,
+                        bottom: position.y + size.height as i32,
+                    },
+                    primary: primary.as_ref().is_some_and(|primary| {
+                        primary.position() == position && primary.name() == monitor.name()
+                    }),
+                    scale_factor: monitor.scale_factor(),
+                    device_name: monitor.name().cloned().unwrap_or_default(),
+                    mirror_of: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Turn screen-capture exclusion of the main window on or off at runtime, so
+/// QA can compare capture results without rebuilding. Turning it off needs
+/// the exit password (returns false for a wrong one, which counts towards
+/// the `attempt_exit` lockout); turning it on needs none.
+#[tauri::command]
+fn set_capture_exclusion(
+    app: AppHandle,
+    security: State<'_, SecurityConfig>,
+    excluded: bool,
+    password: Option<String>,
+) -> Result<bool, String> {
+    if !excluded && !check_exit_password(&security, password.as_deref().unwrap_or_default())? {
+        return Ok(false);
+    }
+    let window = app
+        .get_webview_window("main")
+        .ok_or("main window is not open")?;
+
+    #[cfg(target_os = "windows")]
+    return windows_security::set_capture_excluded(&window, excluded).map(|()| true);
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = (window, excluded);
+        Err("capture exclusion can only be toggled on Windows".into())
+    }
+}
+
+/// Which protections engaged on this machine, for a pre-exam check screen.
+/// `None` (null) means this platform can't tell, never a guess.
+#[derive(Serialize)]
+struct SecurityStatus {
+    platform: &'static str,
+    keyboard_hook_active: Option<bool>,
+    /// Windows: the startup self-test confirmed the hook suppresses keys;
+    /// None if it hasn't run
+    keyboard_suppression_verified: Option<bool>,
+    /// Windows: milliseconds the last successful hook install took; None if
+    /// it never installed
+    hook_install_ms: Option<u64>,
+    single_monitor_ok: Option<bool>,
+    screen_recorder_absent: Option<bool>,
+    remote_session_absent: Option<bool>,
+    devtools_disabled: Option<bool>,
+}
+
+#[tauri::command]
+fn get_security_status(app: AppHandle, security: State<'_, SecurityConfig>) -> SecurityStatus {
+    #[cfg(target_os = "windows")]
+    let (screen_recorder_absent, remote_session_absent) = (
+        Some(
+            !windows_security::running_process_names()
+                .iter()
+                .any(|name| security.is_blocked(name)),
+        ),
+        Some(!windows_security::is_remote_session()),
+    );
+    #[cfg(not(target_os = "windows"))]
+    let (screen_recorder_absent, remote_session_absent) = {
+        let _ = security;
+        (None, None)
+    };
+    #[cfg(target_os = "windows")]
+    let (keyboard_suppression_verified, hook_install_ms) = (
+        windows_security::self_test_passed(),
+        windows_security::hook_install_ms(),
+    );
+    #[cfg(not(target_os = "windows"))]
+    let (keyboard_suppression_verified, hook_install_ms) = (None, None);
+
+    SecurityStatus {
+        platform: std::env::consts::OS,
+        keyboard_hook_active: Some(keyboard_hook_active()),
+        keyboard_suppression_verified,
+        hook_install_ms,
+        single_monitor_ok: monitor_count(&app).map(|count| count <= 1),
+        screen_recorder_absent,
+        remote_session_absent,
+        // Release builds create every webview with devtools(false)
+        devtools_disabled: Some(!cfg!(debug_assertions)),
+    }
+}
+
+#[derive(Serialize)]
+struct PreflightFailure {
+    /// Stable identifier for the frontend, e.g. "multiple-monitors"
+    code: &'static str,
+    /// Explanation to show the student
+    message: String,
+}
+
+#[derive(Serialize)]
+struct PreflightReport {
+    ok: bool,
+    failures: Vec<PreflightFailure>,
+    /// Windows: keyboards and mice attached, for the page to show; more than
+    /// one of each is common (a laptop with an external keyboard), so it
+    /// never fails the check
+    input_devices: Option<InputDevices>,
+    /// `verify_self_integrity`; release builds also fail the check without it
+    self_integrity: bool,
+}
+
+/// Everything on this machine that should keep the quiz from starting, so
+/// onboarding can list it all at once before enabling "Start"
+#[tauri::command]
+fn run_preflight(app: AppHandle, security: State<'_, SecurityConfig>) -> PreflightReport {
+    preflight_report(&app, &security)
+}
+
+/// `run_preflight` for mid-exam, e.g. a proctor confirming a second monitor
+/// was unplugged. Each scan is written to the violation log as
+/// `environment-rescanned`. Every check runs synchronously and closes what it
+/// opened, so repeated calls leave nothing behind.
+#[tauri::command]
+fn rescan_environment(app: AppHandle, security: State<'_, SecurityConfig>) -> PreflightReport {
+    let report = preflight_report(&app, &security);
+    let codes: Vec<&str> = report.failures.iter().map(|failure| failure.code).collect();
+    log::info!("environment rescanned: {codes:?}");
+    log_event(
+        "environment-rescanned",
+        &serde_json::json!({ "ok": report.ok, "failures": codes }),
+    );
+    report
+}
+
+fn preflight_report(app: &AppHandle, security: &SecurityConfig) -> PreflightReport {
+    let mut failures = Vec::new();
+    let mut fail = |code, message: String| failures.push(PreflightFailure { code, message });
+
+    if let Some(count) = monitor_count(app).filter(|&count| count > 1) {
+        fail(
+            "multiple-monitors",
+            format!("{count} displays are connected. Disconnect all but one."),
+        );
+    }
+
+    if !keyboard_hook_active() {
+        fail(
+            "keyboard-hook-inactive",
+            "Keyboard protection isn't active. Restart the lockdown browser.".into(),
+        );
+    }
+
+    let self_integrity = verify_self_integrity();
+    if !self_integrity && !cfg!(debug_assertions) {
+        fail(
+            "integrity-check-failed",
+            "This copy of the lockdown browser has been modified. Reinstall it.".into(),
+        );
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        if windows_security::self_test_passed() == Some(false) {
+            fail(
+                "keyboard-suppression-failed",
+                "This computer doesn't let the lockdown browser block system keys. \
+                 Ask IT to check its group policy or security software."
+                    .into(),
+            );
+        }
+
+        if windows_security::is_remote_session() {
+            fail(
+                "remote-session",
+                "The quiz can't be taken over Remote Desktop.".into(),
+            );
+        }
+
+        let mut recorders: Vec<String> = windows_security::running_process_names()
+            .into_iter()
+            .filter(|name| security.is_blocked(name))
+            .collect();
+        recorders.sort();
+        recorders.dedup();
+        if !recorders.is_empty() {
+            fail(
+                "screen-recorder",
+                format!("Close these programs: {}.", recorders.join(", ")),
+            );
+        }
+
+        if security.block_virtual_machines && windows_security::virtual_machine_artifact().is_some()
+        {
+            fail(
+                "virtual-machine",
+                "The quiz can't be taken inside a virtual machine.".into(),
+            );
+        }
+    }
+    #[cfg(not(target_os = "windows"))]
+    let _ = security;
+
+    #[cfg(target_os = "windows")]
+    let input_devices = windows_security::input_devices();
+    #[cfg(not(target_os = "windows"))]
+    let input_devices = None;
+
+    PreflightReport {
+        ok: failures.is_empty(),
+        failures,
+        input_devices,
+        self_integrity,
+    }
+}
+
+// ============================================================================
+// Exit Password
+// ============================================================================
+
+/// Wrong passwords allowed before `attempt_exit` locks out
+const EXIT_PASSWORD_MAX_FAILURES: u32 = 5;
+
+/// How long `attempt_exit` refuses attempts after too many failures
+const EXIT_PASSWORD_LOCKOUT: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct ExitAttempts {
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+static EXIT_ATTEMPTS: Mutex<ExitAttempts> = Mutex::new(ExitAttempts {
+    failures: 0,
+    locked_until: None,
+});
+
+/// PBKDF2-HMAC-SHA256 with a single 32-byte output block (RFC 8018)
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let prf = HmacSha256::new_from_slice(password).expect("HMAC accepts keys of any length");
+    let mut block = prf.clone();
+    block.update(salt);
+    block.update(&1u32.to_be_bytes());
+    let mut u: [u8; 32] = block.finalize().into_bytes().into();
+    let mut output = u;
+    for _ in 1..iterations {
+        let mut next = prf.clone();
+        next.update(&u);
+        u = next.finalize().into_bytes().into();
+        output
+            .iter_mut()
+            .zip(u)
+            .for_each(|(out, byte)| *out ^= byte);
+    }
+    output
+}
+
+/// Check `password` against a `pbkdf2-sha256$<iterations>$<salt>$<hex>`
+/// hash. Generate one with, e.g.:
+///
+/// ```text
+/// python3 -c "import hashlib; print(hashlib.pbkdf2_hmac('sha256', b'PASSWORD', b'SALT', 600000).hex())"
+/// ```
+fn exit_password_matches(encoded: &str, password: &str) -> Result<bool, String> {
+    let invalid = || "security.exit_password_hash is malformed".to_string();
+    let mut parts = encoded.split('$');
+    if parts.next() != Some("pbkdf2-sha256") {
+        return Err(invalid());
+    }
+    let iterations: u32 = parts
+        .next()
+        .and_then(|iterations| iterations.parse().ok())
+        .filter(|&iterations| iterations > 0)
+        .ok_or_else(invalid)?;
+    let salt = parts.next().ok_or_else(invalid)?;
+    let expected = parts.next().ok_or_else(invalid)?.to_ascii_lowercase();
+
+    let actual = hex(&pbkdf2_sha256(
+        password.as_bytes(),
+        salt.as_bytes(),
+        iterations,
+    ));
+    // Constant-time comparison, so timing doesn't reveal a matching prefix
+    let differs = actual.len() != expected.len()
+        || actual
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            != 0;
+    Ok(!differs)
+}
+
+/// Check a proctor's `password` against `security.exit_password_hash`,
+/// counting failures: after EXIT_PASSWORD_MAX_FAILURES wrong passwords
+/// every attempt is refused for EXIT_PASSWORD_LOCKOUT.
+fn check_exit_password(security: &SecurityConfig, password: &str) -> Result<bool, String> {
+    let encoded = security
+        .exit_password_hash
+        .as_deref()
+        .ok_or("no exit password is configured")?;
+
+    let mut attempts = EXIT_ATTEMPTS.lock().unwrap();
+    if let Some(until) = attempts.locked_until {
+        if Instant::now() < until {
+            return Err("too many attempts; try again later".into());
+        }
+        *attempts = ExitAttempts::default();
+    }
+
+    if !exit_password_matches(encoded, password)? {
+        attempts.failures += 1;
+        log_event(
+            "exit-password-failed",
+            &serde_json::json!({ "failures": attempts.failures }),
+        );
+        if attempts.failures >= EXIT_PASSWORD_MAX_FAILURES {
+            attempts.locked_until = Some(Instant::now() + EXIT_PASSWORD_LOCKOUT);
+        }
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Let a proctor end a stuck session with the configured exit password.
+/// Returns false for a wrong password; see `check_exit_password`.
+#[tauri::command]
+fn attempt_exit(
+    app: AppHandle,
+    security: State<'_, SecurityConfig>,
+    password: String,
+) -> Result<bool, String> {
+    if !check_exit_password(&security, &password)? {
+        return Ok(false);
+    }
+    exit_lockdown(&app, "exit-password");
+    Ok(true)
+}
+
+/// Reset the machine between back-to-back sittings without an OS logout, for
+/// a proctor who knows the exit password: the session ends as on any other
+/// exit (hook released, recovery marker removed), its browser data is
+/// cleared whatever `security.isolate_browser_data` says, and the app
+/// relaunches on the base URL as a new session with fresh counters. Returns
+/// false for a wrong password, which counts towards the `attempt_exit`
+/// lockout.
+#[tauri::command]
+fn restart_clean(
+    app: AppHandle,
+    security: State<'_, SecurityConfig>,
+    password: String,
+) -> Result<bool, String> {
+    if !check_exit_password(&security, &password)? {
+        return Ok(false);
+    }
+    if let Err(error) = clear_browser_data(app.clone()) {
+        log::warn!("failed to clear browser data: {error}");
+    }
+    end_session(&app, "restart-clean");
+    // The relaunch gets the same arguments, including any deep link it was
+    // opened with; this tells it to start on the base URL instead
+    if let Err(error) = fs::write(restart_clean_marker(), b"") {
+        log::error!("failed to write restart marker: {error}");
+    }
+
+    // Under the watchdog, exit without the clean-exit marker and let it do
+    // the relaunch, as for updates
+    if under_watchdog() {
+        log::info!("exiting for the watchdog to relaunch");
+        app.exit(0);
+        return Ok(true);
+    }
+    app.restart();
+}
+
+#[derive(Serialize)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct ProcessInfo {
+    pid: u32,
+    name: String,
+}
+
+/// Running processes on `security.blocked_processes`, so a proctor can see
+/// what is open before acting on it. Always empty off Windows.
+#[tauri::command]
+fn list_forbidden_processes(security: State<'_, SecurityConfig>) -> Vec<ProcessInfo> {
+    #[cfg(target_os = "windows")]
+    return windows_security::running_processes()
+        .into_iter()
+        .filter(|(_, name)| security.is_blocked(name))
+        .map(|(pid, name)| ProcessInfo { pid, name })
+        .collect();
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = security;
+        Vec::new()
+    }
+}
+
+/// Kill a running blocklisted process (e.g. OBS) for a proctor who knows the
+/// exit password. Returns false for a wrong password, which counts towards
+/// the `attempt_exit` lockout. Only processes on `security.blocked_processes`
+/// can be terminated; any other pid is refused, so the page can't use this
+/// to kill arbitrary programs.
+#[tauri::command]
+fn terminate_process(
+    security: State<'_, SecurityConfig>,
+    pid: u32,
+    password: String,
+) -> Result<bool, String> {
+    if !check_exit_password(&security, &password)? {
+        return Ok(false);
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let name = windows_security::terminate_process_if(pid, |name| security.is_blocked(name))
+            .inspect_err(|error| log::warn!("terminate_process refused: {error}"))?;
+        log::warn!("terminated forbidden process {name} ({pid})");
+        log_event(
+            "process-terminated",
+            &serde_json::json!({ "pid": pid, "name": name }),
+        );
+        Ok(true)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = pid;
+        Err("processes can only be terminated on Windows".into())
+    }
+}
+
+// ============================================================================
+// Hard Deadline
+// ============================================================================
+
+/// Time between `deadline-reached` and the forced exit, for the page to
+/// submit what it has
+const DEADLINE_GRACE: Duration = Duration::from_secs(10);
+
+/// End time of the armed deadline, and the sender that cancels it when
+/// dropped, see `arm_hard_deadline`
+static DEADLINE: Mutex<Option<(u64, Sender<()>)>> = Mutex::new(None);
+
+#[derive(Clone, Serialize)]
+struct DeadlinePayload {
+    epoch_secs: u64,
+    grace_secs: u64,
+}
+
+/// Wait out `remaining`, emit `deadline-reached`, then exit after
+/// DEADLINE_GRACE unless the sender is dropped first
+fn run_deadline(app: AppHandle, epoch_secs: u64, remaining: Duration, cancelled: Receiver<()>) {
+    if !matches!(
+        cancelled.recv_timeout(remaining),
+        Err(RecvTimeoutError::Timeout)
+    ) {
+        return;
+    }
+    log::warn!("hard deadline {epoch_secs} reached");
+    emit_violation(
+        &app,
+        "deadline-reached",
+        DeadlinePayload {
+            epoch_secs,
+            grace_secs: DEADLINE_GRACE.as_secs(),
+        },
+    );
+    if matches!(
+        cancelled.recv_timeout(DEADLINE_GRACE),
+        Err(RecvTimeoutError::Timeout)
+    ) {
+        exit_lockdown(&app, "deadline-reached");
+    }
+}
+
+/// Exit at `epoch_secs` (Unix time) whatever the page does: `deadline-reached`
+/// fires then, and the app exits DEADLINE_GRACE later. The remaining time is
+/// measured on the monotonic clock from this call, so changing the system
+/// clock afterwards doesn't move it. Arming again replaces the previous
+/// deadline; moving an armed deadline later is an extension and needs the
+/// exit password (returns false for a wrong one, which counts towards the
+/// `attempt_exit` lockout).
+#[tauri::command]
+fn arm_hard_deadline(
+    app: AppHandle,
+    security: State<'_, SecurityConfig>,
+    epoch_secs: u64,
+    password: Option<String>,
+) -> Result<bool, String> {
+    let mut deadline = DEADLINE.lock().unwrap();
+    let extends = deadline
+        .as_ref()
+        .is_some_and(|(armed, _)| epoch_secs > *armed);
+    if extends && !check_exit_password(&security, password.as_deref().unwrap_or_default())? {
+        return Ok(false);
+    }
+
+    let remaining = (UNIX_EPOCH + Duration::from_secs(epoch_secs))
+        .duration_since(SystemTime::now())
+        .unwrap_or_default();
+    let (cancel, cancelled) = mpsc::channel();
+    // Replacing the sender drops the previous one, which cancels its timer
+    *deadline = Some((epoch_secs, cancel));
+    drop(deadline);
+    log::info!(
+        "hard deadline armed for {epoch_secs} (in {}s)",
+        remaining.as_secs()
+    );
+    log_event(
+        "deadline-armed",
+        &serde_json::json!({ "epoch_secs": epoch_secs }),
+    );
+    thread::spawn(move || run_deadline(app, epoch_secs, remaining, cancelled));
+    Ok(true)
+}
+
+/// Disarm the deadline for a proctor who knows the exit password; also stops
+/// a forced exit still in its grace period. Returns false for a wrong
+/// password, which counts towards the `attempt_exit` lockout.
+#[tauri::command]
+fn cancel_deadline(security: State<'_, SecurityConfig>, password: String) -> Result<bool, String> {
+    if !check_exit_password(&security, &password)? {
+        return Ok(false);
+    }
+    if DEADLINE.lock().unwrap().take().is_some() {
+        log::info!("hard deadline cancelled");
+        log_event("deadline-cancelled", &serde_json::json!({}));
+    }
+    Ok(true)
+}
+
+// ============================================================================
+// Countdown Timer
+// ============================================================================
+
+/// Stops the running timer when dropped, see `start_timer`
+static TIMER: Mutex<Option<Sender<()>>> = Mutex::new(None);
+
+#[derive(Clone, Serialize)]
+struct TimerTickPayload {
+    remaining_secs: u64,
+}
+
+/// Emit `timer-tick` now and on every whole second after `started`, then
+/// `timer-finished` once `duration` is up, unless the sender is dropped first.
+/// Each tick is scheduled from `started` rather than the previous tick, so
+/// slow emits don't make the countdown drift.
+fn run_timer(app: AppHandle, started: Instant, duration: Duration, stopped: Receiver<()>) {
+    let total_secs = duration.as_secs();
+    for elapsed_secs in 0..total_secs {
+        let _ = app.emit_to(
+            "main",
+            "timer-tick",
+            TimerTickPayload {
+                remaining_secs: total_secs - elapsed_secs,
+            },
+        );
+        let next = started + Duration::from_secs(elapsed_secs + 1);
+        let wait = next.saturating_duration_since(Instant::now());
+        if !matches!(stopped.recv_timeout(wait), Err(RecvTimeoutError::Timeout)) {
+            return;
+        }
+    }
+    log::info!("countdown timer finished");
+    let _ = app.emit_to("main", "timer-finished", ());
+}
+
+/// Count down `duration_secs` on a native thread, which keeps time even
+/// while the page's own timers are throttled. Starting again replaces the
+/// running timer.
+#[tauri::command]
+fn start_timer(app: AppHandle, duration_secs: u64) {
+    let (stop, stopped) = mpsc::channel();
+    // Replacing the sender drops the previous one, which stops its thread
+    *TIMER.lock().unwrap() = Some(stop);
+    log::info!("countdown timer started for {duration_secs}s");
+    let duration = Duration::from_secs(duration_secs);
+    let started = Instant::now();
+    thread::spawn(move || run_timer(app, started, duration, stopped));
+}
+
+/// Stop the countdown without emitting `timer-finished`
+#[tauri::command]
+fn stop_timer() {
+    if TIMER.lock().unwrap().take().is_some() {
+        log::info!("countdown timer stopped");
+    }
+}
+
+// ============================================================================
+// Always On Top
+// ============================================================================
+
+/// `window.always_on_top`, set during setup
+static CONFIGURED_ALWAYS_ON_TOP: AtomicBool = AtomicBool::new(true);
+
+/// `window.always_on_top_restore_secs`, set during setup
+static ALWAYS_ON_TOP_RESTORE_SECS: AtomicU64 = AtomicU64::new(120);
+
+/// Whether a proctor has dropped always-on-top, so `keep_topmost` leaves the
+/// window alone until it is restored
+static TOPMOST_SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+/// Cancels the pending automatic restore when dropped, see `set_always_on_top`
+static TOPMOST_RESTORE: Mutex<Option<Sender<()>>> = Mutex::new(None);
+
+#[derive(Clone, Serialize)]
+struct AlwaysOnTopPayload {
+    enabled: bool,
+}
+
+/// Apply `enabled` to the main window and tell the page about it
+fn apply_always_on_top(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    let win = app
+        .get_webview_window("main")
+        .ok_or("main window is not open")?;
+    win.set_always_on_top(enabled)
+        .map_err(|error| error.to_string())?;
+    TOPMOST_SUSPENDED.store(!enabled, Ordering::SeqCst);
+    log_event(
+        "always-on-top-changed",
+        &serde_json::json!({ "enabled": enabled }),
+    );
+    let _ = app.emit_to(
+        "main",
+        "always-on-top-changed",
+        AlwaysOnTopPayload { enabled },
+    );
+    Ok(())
+}
+
+/// Put back the configured always-on-top state after `timeout`, unless the
+/// sender is dropped first
+fn restore_always_on_top_after(app: AppHandle, timeout: Duration, cancelled: Receiver<()>) {
+    if !matches!(
+        cancelled.recv_timeout(timeout),
+        Err(RecvTimeoutError::Timeout)
+    ) {
+        return;
+    }
+    log::warn!("always-on-top restored after {}s", timeout.as_secs());
+    if let Err(error) = apply_always_on_top(&app, CONFIGURED_ALWAYS_ON_TOP.load(Ordering::SeqCst)) {
+        log::warn!("failed to restore always-on-top: {error}");
+    }
+}
+
+/// Whether the main window is currently always on top
+#[tauri::command]
+fn get_always_on_top(app: AppHandle) -> Result<bool, String> {
+    app.get_webview_window("main")
+        .ok_or("main window is not open")?
+        .is_always_on_top()
+        .map_err(|error| error.to_string())
+}
+
+/// Change the main window's always-on-top state, e.g. so a proctor's
+/// screen-sharing tool can come to the front while helping a student.
+/// Dropping it needs the exit password (returns false for a wrong one, which
+/// counts towards the `attempt_exit` lockout), and the configured state comes
+/// back by itself after `window.always_on_top_restore_secs`. Turning it back
+/// on needs no password and cancels the pending restore.
+#[tauri::command]
+fn set_always_on_top(
+    app: AppHandle,
+    security: State<'_, SecurityConfig>,
+    enabled: bool,
+    password: Option<String>,
+) -> Result<bool, String> {
+    if enabled {
+        TOPMOST_RESTORE.lock().unwrap().take();
+        apply_always_on_top(&app, true)?;
+        return Ok(true);
+    }
+
+    if !check_exit_password(&security, password.as_deref().unwrap_or_default())? {
+        return Ok(false);
+    }
+    apply_always_on_top(&app, false)?;
+    let (cancel, cancelled) = mpsc::channel();
+    // Replacing the sender drops the previous one, which cancels its restore
+    *TOPMOST_RESTORE.lock().unwrap() = Some(cancel);
+    let timeout = Duration::from_secs(ALWAYS_ON_TOP_RESTORE_SECS.load(Ordering::SeqCst));
+    thread::spawn(move || restore_always_on_top_after(app, timeout, cancelled));
+    Ok(true)
+}
+
+// ============================================================================
+// Proctor Banner
+// ============================================================================
+
+/// Label of the window `show_proctor_banner` opens
+const PROCTOR_BANNER_LABEL: &str = "proctor-banner";
+
+/// Logical size of the banner, placed centered at the top of the main
+/// window's monitor
+const PROCTOR_BANNER_WIDTH: f64 = 640.0;
+const PROCTOR_BANNER_HEIGHT: f64 = 72.0;
+const PROCTOR_BANNER_MARGIN: f64 = 16.0;
+
+/// Longest message `show_proctor_banner` accepts
+const MAX_PROCTOR_BANNER_LEN: usize = 300;
+
+/// Cancels the pending close of the banner when dropped, see
+/// `show_proctor_banner`
+static PROCTOR_BANNER: Mutex<Option<Sender<()>>> = Mutex::new(None);
+
+/// Top-left corner for the banner, or None if the monitor is unknown
+fn proctor_banner_position(app: &AppHandle) -> Option<(f64, f64)> {
+    let monitor = app.get_webview_window("main")?.current_monitor().ok()??;
+    let scale = monitor.scale_factor();
+    let origin = monitor.position().to_logical::<f64>(scale);
+    let size = monitor.size().to_logical::<f64>(scale);
+    Some((
+        origin.x + (size.width - PROCTOR_BANNER_WIDTH).max(0.0) / 2.0,
+        origin.y + PROCTOR_BANNER_MARGIN,
+    ))
+}
+
+/// Close the banner after `duration`, unless the sender is dropped first
+fn close_proctor_banner_after(app: AppHandle, duration: Duration, cancelled: Receiver<()>) {
+    if !matches!(
+        cancelled.recv_timeout(duration),
+        Err(RecvTimeoutError::Timeout)
+    ) {
+        return;
+    }
+    if let Some(window) = app.get_webview_window(PROCTOR_BANNER_LABEL) {
+        // Not close(): the banner prevents close requests like the cover
+        // windows do
+        let _ = window.destroy();
+    }
+}
+
+/// Show `text` from a proctor (e.g. "5 minutes remaining") in a small
+/// always-on-top window above the quiz for `duration_secs`. The student
+/// can't dismiss it, it doesn't depend on the quiz page's JS, and focus
+/// moving to it isn't counted as a focus loss. A new message replaces the
+/// one showing and restarts the timer.
+#[tauri::command]
+async fn show_proctor_banner(
+    app: AppHandle,
+    text: String,
+    duration_secs: u32,
+) -> Result<(), String> {
+    if duration_secs == 0 {
+        return Err("duration_secs must be at least 1".into());
+    }
+    let text: String = text.chars().take(MAX_PROCTOR_BANNER_LEN).collect();
+    let message = serde_json::to_string(&text).expect("banner text should serialize to JSON");
+
+    if let Some(window) = app.get_webview_window(PROCTOR_BANNER_LABEL) {
+        window
+            .eval(format!(
+                "window.__UNDERSTANDLY_LOCKDOWN_SET_BANNER__?.({message});"
+            ))
+            .map_err(|error| error.to_string())?;
+    } else {
+        let (x, y) = proctor_banner_position(&app).unwrap_or((0.0, 0.0));
+        WebviewWindowBuilder::new(
+            &app,
+            PROCTOR_BANNER_LABEL,
+            WebviewUrl::App("proctor-banner.html".into()),
+        )
+        .initialization_script(format!(
+            "window.__UNDERSTANDLY_LOCKDOWN_BANNER_TEXT__ = {message};"
+        ))
+        .devtools(cfg!(debug_assertions))
+        .title("Proctor message")
+        .inner_size(PROCTOR_BANNER_WIDTH, PROCTOR_BANNER_HEIGHT)
+        .position(x, y)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .decorations(false)
+        .resizable(false)
+        .maximizable(false)
+        .minimizable(false)
+        .closable(false)
+        .focused(false)
+        .build()
+        .map_err(|error| error.to_string())?;
+    }
+
+    log::info!("proctor banner shown for {duration_secs}s");
+    log_event(
+        "proctor-banner",
+        &serde_json::json!({ "text": text, "duration_secs": duration_secs }),
+    );
+    let (cancel, cancelled) = mpsc::channel();
+    // Replacing the sender drops the previous one, which cancels its close
+    *PROCTOR_BANNER.lock().unwrap() = Some(cancel);
+    let duration = Duration::from_secs(duration_secs.into());
+    thread::spawn(move || close_proctor_banner_after(app, duration, cancelled));
+    Ok(())
+}
+
+// ============================================================================
+// Attestation
+// ============================================================================
+
+/// Shared secret for `attest`, injected at compile time from the
+/// `LOCKDOWN_ATTESTATION_SECRET` environment variable (a GitHub secret in the
+/// release workflow). Builds without it refuse to attest.
+const ATTESTATION_SECRET: Option<&str> = option_env!("LOCKDOWN_ATTESTATION_SECRET");
+
+/// Longest nonce `attest` accepts
+const MAX_NONCE_LEN: usize = 256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Serialize)]
+struct Attestation {
+    version: &'static str,
+    keyboard_hook_active: bool,
+    safe_mode: bool,
+    /// Lowercase hex HMAC-SHA256; see `attest`
+    signature: String,
+}
+
+/// Let the server confirm the page is running inside this browser.
+///
+/// Signing scheme, for the server-side verifier:
+///
+/// ```text
+/// message   = nonce + "\n" + version + "\n" + ("true" | "false")
+///                   + "\n" + ("true" | "false")
+/// signature = hex(HMAC-SHA256(key = LOCKDOWN_ATTESTATION_SECRET, message))
+/// ```
+///
+/// `version` and the booleans are the `version`, `keyboard_hook_active` and
+/// `safe_mode` fields returned alongside the signature, in that order; a
+/// server should refuse a session that reports safe mode. The server should
+/// issue a fresh random nonce per check, reject reused nonces, and compare
+/// signatures in constant time.
+#[tauri::command]
+fn attest(config: State<'_, PublicConfig>, nonce: String) -> Result<Attestation, String> {
+    let secret = ATTESTATION_SECRET.ok_or("this build has no attestation secret")?;
+    if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+        return Err(format!("nonce must be 1-{MAX_NONCE_LEN} bytes"));
+    }
+
+    let version = env!("CARGO_PKG_VERSION");
+    let keyboard_hook_active = keyboard_hook_active();
+    let safe_mode = config.safe_mode;
+    let message = format!("{nonce}\n{version}\n{keyboard_hook_active}\n{safe_mode}");
+
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    let signature = hex(&mac.finalize().into_bytes());
+
+    Ok(Attestation {
+        version,
+        keyboard_hook_active,
+        safe_mode,
+        signature,
+    })
+}
+
+// ============================================================================
+// Self Integrity
+// ============================================================================
+
+/// Length of the tag `scripts/stamp-self-hash.mjs` finds `SELF_HASH_SLOT` by
+const SELF_HASH_TAG_LEN: usize = 16;
+
+/// The executable's expected SHA-256, written into the built binary by
+/// `scripts/stamp-self-hash.mjs`: a tag, then 32 bytes the compiler leaves
+/// zero. The digest covers the whole file with those 32 bytes zeroed, so it
+/// only exists once the binary is otherwise final.
+#[used]
+static SELF_HASH_SLOT: [u8; SELF_HASH_TAG_LEN + 32] = {
+    let tag = b"UL-SELF-SHA256::";
+    let mut slot = [0; SELF_HASH_TAG_LEN + 32];
+    let mut i = 0;
+    while i < SELF_HASH_TAG_LEN {
+        slot[i] = tag[i];
+        i += 1;
+    }
+    slot
+};
+
+static SELF_INTEGRITY: OnceLock<bool> = OnceLock::new();
+
+/// Whether the running executable is the one that was released, so the
+/// server can refuse a patched client (e.g. one with the keyboard hook
+/// nopped out). A valid Authenticode (Windows) or Developer ID (macOS)
+/// signature decides it when there is one, otherwise the digest stamped into
+/// `SELF_HASH_SLOT`. Unsigned, unstamped builds report `false`. Checked once
+/// per session.
+#[tauri::command]
+fn verify_self_integrity() -> bool {
+    *SELF_INTEGRITY.get_or_init(|| {
+        let verified = self_integrity();
+        if !verified {
+            log::error!("this executable failed its integrity check");
+        }
+        verified
+    })
+}
+
+fn self_integrity() -> bool {
+    #[cfg(target_os = "windows")]
+    if let Some(valid) = windows_security::authenticode_valid() {
+        return valid;
+    }
+    #[cfg(target_os = "macos")]
+    if let Some(valid) = macos_security::code_signature_valid() {
+        return valid;
+    }
+    self_hash_matches()
+}
+
+/// Whether the executable on disk hashes to the digest in `SELF_HASH_SLOT`
+fn self_hash_matches() -> bool {
+    // A plain read would let the compiler fold in the zeros it saw at build
+    // time
+    let slot = unsafe { std::ptr::read_volatile(&SELF_HASH_SLOT) };
+    let expected = &slot[SELF_HASH_TAG_LEN..];
+    if expected.iter().all(|&byte| byte == 0) {
+        return false;
+    }
+    let Ok(mut exe) = std::env::current_exe().and_then(fs::read) else {
+        return false;
+    };
+    let Some(at) = exe.windows(slot.len()).position(|window| window == slot) else {
+        return false;
+    };
+    exe[at + SELF_HASH_TAG_LEN..at + slot.len()].fill(0);
+    Sha256::digest(&exe).as_slice() == expected
+}
+
+// ============================================================================
+// Violation Log
+// ============================================================================
+
+/// Key for the violation log chain. Release builds reuse the attestation
+/// secret so only the server can recompute the chain; without it the chain
+/// still detects edits and deletions, but anyone with the source could redo it.
+const VIOLATION_LOG_KEY: &str = match ATTESTATION_SECRET {
+    Some(secret) => secret,
+    None => "understandly-lockdown-violation-log",
+};
+
+/// Chain value before the first line of a new log
+const VIOLATION_LOG_GENESIS: &str = "";
+
+/// Append-only JSON lines file in the app data dir, one line per reported
+/// event. Each line ends in `"mac":"<hex>"`, computed as:
+///
+/// ```text
+/// mac = hex(HMAC-SHA256(key = VIOLATION_LOG_KEY, previous_mac + "\n" + body))
+/// ```
+///
+/// where `body` is the line with its trailing `,"mac":"…"` removed (so it ends
+/// in `}`) and `previous_mac` is the mac of the line before, or empty for the
+/// first line. Editing, removing or reordering any line breaks every mac after
+/// it. The log is written locally either way, whether or not a proctoring
+/// server is reachable.
+struct ViolationLog {
+    path: PathBuf,
+    state: Mutex<ViolationLogState>,
+}
+
+struct ViolationLogState {
+    file: File,
+    last_mac: String,
+}
+
+#[derive(Serialize)]
+struct ViolationEntry<'a> {
+    /// Unix time in milliseconds
+    timestamp_ms: u128,
+    event: &'a str,
+    details: serde_json::Value,
+}
+
+static VIOLATION_LOG: OnceLock<ViolationLog> = OnceLock::new();
+
+impl ViolationLog {
+    /// Open (or create) the log at `path`, continuing the chain from its
+    /// last line
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let last_mac = fs::read_to_string(&path)
+            .ok()
+            .and_then(|log| {
+                let last = log.lines().last()?;
+                let value: serde_json::Value = serde_json::from_str(last).ok()?;
+                Some(value.get("mac")?.as_str()?.to_owned())
+            })
+            .unwrap_or_else(|| VIOLATION_LOG_GENESIS.into());
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            state: Mutex::new(ViolationLogState { file, last_mac }),
+        })
+    }
+
+    fn append(&self, event: &str, details: serde_json::Value) -> std::io::Result<()> {
+        let body = serde_json::to_string(&ViolationEntry {
+            timestamp_ms: unix_time_ms(),
+            event,
+            details,
+        })?;
+
+        let mut state = self.state.lock().unwrap();
+        let mut mac = HmacSha256::new_from_slice(VIOLATION_LOG_KEY.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(state.last_mac.as_bytes());
+        mac.update(b"\n");
+        mac.update(body.as_bytes());
+        let mac = hex(&mac.finalize().into_bytes());
+
+        let unsigned = body.strip_suffix('}').unwrap_or(&body);
+        writeln!(state.file, "{unsigned},\"mac\":\"{mac}\"}}")?;
+        state.last_mac = mac;
+        Ok(())
+    }
+
+    /// Push everything written so far to disk, before the process exits
+    fn flush(&self) -> std::io::Result<()> {
+        self.state.lock().unwrap().file.sync_data()
+    }
+}
+
+/// Violations kept in memory for `get_recent_violations`
+const MAX_RECENT_VIOLATIONS: usize = 200;
+
+/// Ordered, so `alarm.min_severity` can be compared against
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    fn of(kind: &str) -> Self {
+        match kind {
+            "forbidden-process" | "idle-timeout" | "lockdown-violation" | "hook-reinstalled" => {
+                Self::Critical
+            }
+            "blocked-key" | "lockdown-state" | "stage-changed" | "deadline-reached" => Self::Info,
+            _ => Self::Warning,
+        }
+    }
+}
+
+/// Payload of the unified `violations` event: every detector event, tagged
+/// with its name as `kind`
+#[derive(Clone, Serialize)]
+struct Violation {
+    kind: String,
+    severity: Severity,
+    details: serde_json::Value,
+    /// Unix time in milliseconds
+    timestamp_ms: u128,
+}
+
+static RECENT_VIOLATIONS: Mutex<VecDeque<Violation>> = Mutex::new(VecDeque::new());
+
+/// How many times each event has been emitted this session, for
+/// `SessionSummary`
+static VIOLATION_COUNTS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+/// Every key press the hook suppressed, including the repeats `blocked-key`
+/// throttles away
+static BLOCKED_KEY_PRESSES: AtomicU64 = AtomicU64::new(0);
+
+/// Unix time in milliseconds
+fn unix_time_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default()
+}
+
+/// Append `event` to the violation log, if it is open
+fn log_event<S: Serialize>(event: &str, details: &S) {
+    if let Some(log) = VIOLATION_LOG.get() {
+        let details = serde_json::to_value(details).unwrap_or_default();
+        if let Err(error) = log.append(event, details) {
+            log::error!("failed to write violation log: {error}");
+        }
+    }
+}
+
+/// Write `event` to the violation log and emit it to the main window, both
+/// on its own and on the unified `violations` stream
+fn emit_violation<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    log_event(event, &payload);
+    let violation = Violation {
+        kind: event.into(),
+        severity: Severity::of(event),
+        details: serde_json::to_value(&payload).unwrap_or_default(),
+        timestamp_ms: unix_time_ms(),
+    };
+    *VIOLATION_COUNTS
+        .lock()
+        .unwrap()
+        .entry(event.into())
+        .or_default() += 1;
+    remember_violation(violation.clone());
+    let _ = app.emit_to("main", event, payload);
+    trigger_alarm(app, violation.severity);
+    let _ = app.emit_to("main", "violations", violation);
+}
+
+/// Keep `violation` for `get_recent_violations`, dropping the oldest once
+/// MAX_RECENT_VIOLATIONS are kept
+fn remember_violation(violation: Violation) {
+    let mut recent = RECENT_VIOLATIONS.lock().unwrap();
+    if recent.len() == MAX_RECENT_VIOLATIONS {
+        recent.pop_front();
+    }
+    recent.push_back(violation);
+}
+
+// ============================================================================
+// Alarm
+// ============================================================================
+
+/// `alarm`, set at startup when it is enabled
+static ALARM: OnceLock<AlarmConfig> = OnceLock::new();
+
+/// Defines `__UNDERSTANDLY_LOCKDOWN_ALARM__(durationMs, sound)` on every
+/// page, which flashes a red overlay (and beeps, if `sound`) for the given
+/// time. The overlay lets clicks through and nothing takes focus, so the
+/// alarm can't count as a focus loss itself. Calling it again while it runs
+/// extends it. The beep is synthesized, since a bundled file would be mixed
+/// content on an https quiz; the webview may keep it silent until the page
+/// has had a click or key press.
+fn alarm_script() -> String {
+    format!(
+        r#"
+        (function () {{
+            var overlay = null;
+            var flashTimer = 0;
+            var stopTimer = 0;
+            var audio = null;
+            var oscillator = null;
+            var stop = function () {{
+                clearInterval(flashTimer);
+                if (overlay) overlay.remove();
+                overlay = null;
+                if (oscillator) oscillator.stop();
+                oscillator = null;
+            }};
+            var beep = function () {{
+                try {{
+                    audio = audio || new AudioContext();
+                    if (audio.state === 'suspended') audio.resume();
+                    oscillator = audio.createOscillator();
+                    var gain = audio.createGain();
+                    oscillator.type = 'square';
+                    oscillator.frequency.value = 880;
+                    gain.gain.value = 0.2;
+                    oscillator.connect(gain).connect(audio.destination);
+                    oscillator.start();
+                }} catch (e) {{}}
+            }};
+            Object.defineProperty(window, '__UNDERSTANDLY_LOCKDOWN_ALARM__', {{
+                value: function (durationMs, sound) {{
+                    clearTimeout(stopTimer);
+                    stopTimer = setTimeout(stop, durationMs);
+                    if (overlay) return;
+                    overlay = document.createElement('div');
+                    overlay.id = '{ALARM_ID}';
+                    overlay.setAttribute('aria-hidden', 'true');
+                    overlay.style.cssText = 'position:fixed;inset:0;z-index:2147483647;' +
+                        'pointer-events:none;background:#d00000;opacity:0.6';
+                    document.documentElement.appendChild(overlay);
+                    var on = true;
+                    flashTimer = setInterval(function () {{
+                        on = !on;
+                        overlay.style.opacity = on ? '0.6' : '0';
+                        if (oscillator) oscillator.frequency.value = on ? 880 : 660;
+                    }}, 250);
+                    if (sound) beep();
+                }},
+                writable: false,
+                configurable: false
+            }});
+        }})();
+        "#
+    )
+}
+
+/// Id of the overlay `alarm_script` flashes
+const ALARM_ID: &str = "__understandly_lockdown_alarm__";
+
+/// Set off the alarm in the main window if `severity` reaches
+/// `alarm.min_severity`
+fn trigger_alarm(app: &AppHandle, severity: Severity) {
+    let Some(alarm) = ALARM.get().filter(|alarm| severity >= alarm.min_severity) else {
+        return;
+    };
+    if let Some(window) = app.get_webview_window("main") {
+        let duration_ms = u64::from(alarm.duration_secs) * 1000;
+        let script = format!(
+            "window.__UNDERSTANDLY_LOCKDOWN_ALARM__?.({duration_ms}, {});",
+            alarm.sound
+        );
+        if let Err(error) = window.eval(script) {
+            log::warn!("failed to sound the alarm: {error}");
+        }
+    }
+}
+
+/// The `limit` most recent violations this session, oldest first
+#[tauri::command]
+fn get_recent_violations(limit: usize) -> Vec<Violation> {
+    let recent = RECENT_VIOLATIONS.lock().unwrap();
+    let skip = recent.len().saturating_sub(limit);
+    recent.iter().skip(skip).cloned().collect()
+}
+
+#[derive(Clone, Serialize)]
+struct SessionSummary {
+    /// See `session_id`
+    session_id: &'static str,
+    /// Unix time in milliseconds
+    started_ms: u128,
+    /// Unix time in milliseconds
+    ended_ms: u128,
+    duration_secs: u64,
+    /// Times each event was emitted, by name, e.g. `focus-lost`
+    violations: BTreeMap<String, u64>,
+    blocked_key_presses: u64,
+}
+
+impl SessionSummary {
+    fn now() -> Self {
+        let ended_ms = unix_time_ms();
+        let running = APP_STARTED.get().map(Instant::elapsed).unwrap_or_default();
+        Self {
+            session_id: session_id(),
+            started_ms: ended_ms.saturating_sub(running.as_millis()),
+            ended_ms,
+            duration_secs: running.as_secs(),
+            violations: VIOLATION_COUNTS.lock().unwrap().clone(),
+            blocked_key_presses: BLOCKED_KEY_PRESSES.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counts of everything the detectors reported so far, for the page to send
+/// to its server along with the submission. `exit_lockdown` also emits it
+/// as `session-summary` and writes it to the violation log.
+#[tauri::command]
+fn get_session_summary() -> SessionSummary {
+    SessionSummary::now()
+}
+
+/// Title of the window that currently has the foreground, on Windows
+#[tauri::command]
+fn get_foreground_window_title() -> Option<String> {
+    #[cfg(target_os = "windows")]
+    return windows_security::foreground_window().0;
+
+    #[cfg(not(target_os = "windows"))]
+    None
+}
+
+/// Path of the violation log, for support to collect after a session
+#[tauri::command]
+fn export_violation_log() -> Result<String, String> {
+    VIOLATION_LOG
+        .get()
+        .map(|log| log.path.display().to_string())
+        .ok_or_else(|| "violation log is unavailable".into())
+}
+
+/// Timeout of each `upload_violations` request
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests `upload_violations` makes before giving up
+const UPLOAD_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled for each one after it
+const UPLOAD_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Why `upload_violations` failed, for the page to show
+#[derive(Serialize)]
+struct UploadError {
+    /// "disabled", "endpoint-not-allowed", "log-unavailable", "network" or
+    /// "http"
+    kind: &'static str,
+    /// The server's response status, for "http"
+    status: Option<u16>,
+    message: String,
+}
+
+impl UploadError {
+    fn new(kind: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            status: None,
+            message: message.into(),
+        }
+    }
+}
+
+/// POST the violation log, as written (one HMAC-chained JSON entry per
+/// line), to `endpoint` with `auth` as the bearer token. The endpoint's
+/// origin must be in `security.violation_upload_origins`, so the page can't
+/// send the log anywhere else. Network errors and 5xx responses are retried
+/// up to UPLOAD_ATTEMPTS times; 4xx responses are not.
+#[tauri::command]
+async fn upload_violations(
+    security: State<'_, SecurityConfig>,
+    endpoint: String,
+    auth: String,
+) -> Result<(), UploadError> {
+    if security.violation_upload_origins.is_empty() {
+        return Err(UploadError::new(
+            "disabled",
+            "violation uploads are not enabled",
+        ));
+    }
+    let url = Url::parse(&endpoint)
+        .map_err(|error| UploadError::new("endpoint-not-allowed", error.to_string()))?;
+    let origin = url.origin().ascii_serialization();
+    let allowed = url.scheme() == "https"
+        && security
+            .violation_upload_origins
+            .iter()
+            .any(|allowed| allowed.trim_end_matches('/') == origin);
+    if !allowed {
+        return Err(UploadError::new(
+            "endpoint-not-allowed",
+            format!("{origin} is not an allowed upload origin"),
+        ));
+    }
+
+    let log = VIOLATION_LOG
+        .get()
+        .ok_or_else(|| UploadError::new("log-unavailable", "violation log is unavailable"))?;
+    log.flush()
+        .map_err(|error| UploadError::new("log-unavailable", error.to_string()))?;
+    let body = fs::read(&log.path)
+        .map_err(|error| UploadError::new("log-unavailable", error.to_string()))?;
+
+    let client = reqwest::Client::builder()
+        .timeout(UPLOAD_TIMEOUT)
+        .build()
+        .map_err(|error| UploadError::new("network", error.to_string()))?;
+    let mut delay = UPLOAD_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let response = client
+            .post(url.clone())
+            .bearer_auth(&auth)
+            .header("Content-Type", "application/x-ndjson")
+            .body(body.clone())
+            .send()
+            .await;
+        let error = match response {
+            Ok(response) if response.status().is_success() => {
+                log::info!("violation log uploaded to {origin}");
+                return Ok(());
+            }
+            Ok(response) => {
+                let status = response.status();
+                let error = UploadError {
+                    kind: "http",
+                    status: Some(status.as_u16()),
+                    message: format!("server answered {status}"),
+                };
+                if !status.is_server_error() {
+                    return Err(error);
+                }
+                error
+            }
+            Err(error) => UploadError::new("network", error.to_string()),
+        };
+        if attempt == UPLOAD_ATTEMPTS {
+            log::warn!("violation log upload failed: {}", error.message);
+            return Err(error);
+        }
+        // No timer of our own on the async runtime; this keeps tokio out of
+        // the direct dependencies
+        let _ = tauri::async_runtime::spawn_blocking(move || thread::sleep(delay)).await;
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+// ============================================================================
+// Proctoring Checkpoints
+// ============================================================================
+
+#[derive(Serialize)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct CheckpointData {
+    /// Unix time in milliseconds
+    timestamp_ms: u128,
+    /// Base64 PNG of the main window's client area
+    screenshot_png: String,
+    width: u32,
+    height: u32,
+    monitor_count: Option<usize>,
+    /// Title of the window that had the foreground when the capture was taken
+    foreground_window_title: Option<String>,
+    /// Executable name of the app that had the foreground
+    foreground_process: Option<String>,
+}
+
+/// PNG-encode top-down RGB rows
+#[cfg(target_os = "windows")]
+fn encode_png(width: u32, height: u32, rgb: &[u8]) -> Result<Vec<u8>, png::EncodingError> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgb)?;
+    writer.finish()?;
+    Ok(png)
+}
+
+/// Evidence for the proctoring server, taken on the page's own timer: a
+/// screenshot of the quiz window plus the monitor count and foreground window.
+/// The screenshot still works with `security.exclude_from_capture` on, which
+/// only hides the window from other apps' captures.
+#[tauri::command]
+async fn capture_checkpoint(app: AppHandle) -> Result<CheckpointData, String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or("main window is not open")?;
+
+    #[cfg(target_os = "windows")]
+    {
+        use base64::Engine as _;
+
+        tauri::async_runtime::spawn_blocking(move || -> Result<CheckpointData, String> {
+            let (width, height, rgb) = windows_security::capture_window(&window)?;
+            let png = encode_png(width, height, &rgb).map_err(|error| error.to_string())?;
+            let (foreground_window_title, foreground_process) =
+                windows_security::foreground_window();
+            Ok(CheckpointData {
+                timestamp_ms: unix_time_ms(),
+                screenshot_png: base64::engine::general_purpose::STANDARD.encode(png),
+                width,
+                height,
+                monitor_count: monitor_count(&app),
+                foreground_window_title,
+                foreground_process,
+            })
+        })
+        .await
+        .map_err(|error| error.to_string())?
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = window;
+        Err("checkpoints can only be captured on Windows".into())
+    }
+}
+
+// ============================================================================
+// Auto-Updater
+// ============================================================================
+
+fn close_loading_recovery(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window("loading-recovery") {
+        window.close().map_err(|error| error.to_string())?;
+    }
+    if let Some(main_win) = app.get_webview_window("main") {
+        move_to_quiz_monitor(app, &main_win)?;
+    }
+    Ok(())
+}
+
+fn set_loading_recovery_updating(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("loading-recovery") {
+        let _ = window.eval("window.__UNDERSTANDLY_LOCKDOWN_UPDATING__?.();");
+    }
+}
+
+fn restore_loading_recovery(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("loading-recovery") {
+        let _ = window.eval("window.__UNDERSTANDLY_LOCKDOWN_RESTORE_LOADING__?.();");
+    }
+}
+
+fn finish_update_check(app: &AppHandle, state: &QuizSessionState) {
+    let ready_requested = {
+        let mut phase = state.phase.lock().unwrap();
+        match &*phase {
+            QuizPhase::CheckingUpdate { ready_requested }
+            | QuizPhase::Updating { ready_requested } => {
+                let requested = *ready_requested;
+                *phase = if requested {
+                    QuizPhase::Ready
+                } else {
+                    QuizPhase::Loading
+                };
+                requested
+            }
+            QuizPhase::Loading | QuizPhase::Ready => false,
+        }
+    };
+
+    if ready_requested {
+        let _ = close_loading_recovery(app);
+    } else {
+        restore_loading_recovery(app);
+    }
+}
+
+async fn check_for_updates(
+    app: AppHandle,
+    state: Arc<QuizSessionState>,
+) -> tauri_plugin_updater::Result<()> {
+    let check_result = app
+        .updater_builder()
+        // This timeout also covers the installer download. Keep it bounded so a
+        // failed update cannot hold the loading screen indefinitely, while
+        // allowing normal home connections enough time to fetch the bundle.
+        .timeout(Duration::from_secs(60))
+        .build()?
+        .check()
+        .await;
+
+    let update = match check_result {
+        Ok(Some(update)) => update,
+        Ok(None) => {
+            finish_update_check(&app, &state);
+            return Ok(());
+        }
+        Err(error) => {
+            finish_update_check(&app, &state);
+            return Err(error);
+        }
+    };
+
+    let install_update = {
+        let mut phase = state.phase.lock().unwrap();
+        match &*phase {
+            QuizPhase::CheckingUpdate { ready_requested } => {
+                let requested = *ready_requested;
+                *phase = QuizPhase::Updating {
+                    ready_requested: requested,
+                };
+                true
+            }
+            QuizPhase::Loading | QuizPhase::Ready | QuizPhase::Updating { .. } => false,
+        }
+    };
+
+    if !install_update {
+        log::info!(
+            "update {} deferred because the quiz is active",
+            update.version
+        );
+        return Ok(());
+    }
+
+    log::info!("installing update {}", update.version);
+    set_loading_recovery_updating(&app);
+
+    if let Err(error) = download_and_restart(&app, &update).await {
+        finish_update_check(&app, &state);
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Download and install `update`, then restart into it. Only returns early on
+/// failure or when the watchdog will do the relaunch.
+async fn download_and_restart(
+    app: &AppHandle,
+    update: &Update,
+) -> tauri_plugin_updater::Result<()> {
+    update.download_and_install(|_, _| {}, || {}).await?;
+
+    // Under the watchdog, exit and let it relaunch the updated binary as its
+    // own child; a self-restart would leave the new process unwatched
+    if under_watchdog() {
+        log::info!("update installed; exiting for the watchdog to relaunch");
+        app.exit(0);
+        return Ok(());
+    }
+
+    log::info!("update installed; restarting");
+    app.restart();
+}
+
+#[derive(Clone, Serialize)]
+struct UpdateInfo {
+    current_version: String,
+    /// None when the running version is the latest
+    available_version: Option<String>,
+    exam_active: bool,
+}
+
+/// Tell Rust whether an exam is in progress. `mark_quiz_ready` already sets
+/// it; clear it after submission so a pending update can be applied.
+#[tauri::command]
+fn set_exam_active(state: State<'_, Arc<QuizSessionState>>, active: bool) {
+    state.exam_active.store(active, Ordering::SeqCst);
+}
+
+/// Check for an update without installing it. An update found while an exam
+/// is active is also announced as `update-deferred`, so the page can apply it
+/// after submission.
+#[tauri::command]
+async fn check_for_update(
+    app: AppHandle,
+    state: State<'_, Arc<QuizSessionState>>,
+) -> Result<UpdateInfo, String> {
+    let update = app
+        .updater()
+        .map_err(|error| error.to_string())?
+        .check()
+        .await
+        .map_err(|error| error.to_string())?;
+
+    let info = UpdateInfo {
+        current_version: app.package_info().version.to_string(),
+        available_version: update.map(|update| update.version),
+        exam_active: state.exam_active.load(Ordering::SeqCst),
+    };
+    if info.exam_active && info.available_version.is_some() {
+        let _ = app.emit_to("main", "update-deferred", info.clone());
+    }
+    Ok(info)
+}
+
+/// Install the available update and restart. Rejected while an exam is
+/// active or the startup check is still running.
+#[tauri::command]
+async fn apply_update(
+    app: AppHandle,
+    state: State<'_, Arc<QuizSessionState>>,
+) -> Result<(), String> {
+    let exam_active = || state.exam_active.load(Ordering::SeqCst);
+    if exam_active() {
+        return Err("an exam is in progress; apply the update after submission".into());
+    }
+    if matches!(
+        *state.phase.lock().unwrap(),
+        QuizPhase::CheckingUpdate { .. } | QuizPhase::Updating { .. }
+    ) {
+        return Err("the startup update check is still running".into());
+    }
+
+    let update = app
+        .updater()
+        .map_err(|error| error.to_string())?
+        .check()
+        .await
+        .map_err(|error| error.to_string())?
+        .ok_or("no update is available")?;
+
+    // The check is a network round trip; the exam may have started meanwhile
+    if exam_active() {
+        return Err("an exam is in progress; apply the update after submission".into());
+    }
+
+    log::info!("installing update {} on request", update.version);
+    download_and_restart(&app, &update)
+        .await
+        .map_err(|error| error.to_string())
+}
+
+// ============================================================================
+// Blocked Key Events
+// ============================================================================
+
+/// Minimum gap between two `blocked-key` events for the same combination, so
+/// a held key's auto-repeat doesn't flood the page.
+#[cfg(target_os = "windows")]
+const BLOCKED_KEY_THROTTLE: Duration = Duration::from_secs(1);
+
+#[cfg(target_os = "windows")]
+#[derive(Clone, Serialize)]
+struct BlockedKeyPayload {
+    combo: String,
+    vk_code: u32,
+    /// Milliseconds since the app started (monotonic)
+    timestamp_ms: u64,
+}
+
+/// Relay keys suppressed by the keyboard hook to the main window as
+/// `blocked-key` events, and `warn` combinations it let through as
+/// `warned-key`. Runs on its own thread for the life of the app.
+#[cfg(target_os = "windows")]
+fn forward_blocked_keys(app: AppHandle, blocked_keys: Receiver<windows_security::BlockedKey>) {
+    let mut last_emitted: HashMap<String, Instant> = HashMap::new();
+
+    for key in blocked_keys {
+        if key.suppressed {
+            BLOCKED_KEY_PRESSES.fetch_add(1, Ordering::Relaxed);
+        }
+        let combo = key.combo_name();
+        let throttled = last_emitted
+            .get(&combo)
+            .is_some_and(|last| key.at.duration_since(*last) < BLOCKED_KEY_THROTTLE);
+        if throttled {
+            continue;
+        }
+        last_emitted.insert(combo.clone(), key.at);
+
+        let payload = BlockedKeyPayload {
+            combo,
+            vk_code: key.vk_code,
+            timestamp_ms: monotonic_ms(key.at),
+        };
+        let event = if key.suppressed {
+            "blocked-key"
+        } else {
+            "warned-key"
+        };
+        emit_violation(&app, event, payload);
+    }
+}
+
+// ============================================================================
+// Remote Policy
+// ============================================================================
+
+/// minisign public key (the base64 line of `minisign.pub`) policies must be
+/// signed with, injected at compile time from `LOCKDOWN_POLICY_PUBKEY`.
+/// Builds without it never apply a policy.
+const POLICY_PUBKEY: Option<&str> = option_env!("LOCKDOWN_POLICY_PUBKEY");
+
+/// Timeout for each of the policy and signature requests, so an unreachable
+/// server only briefly delays startup
+const POLICY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PolicyMode {
+    /// Lists in the policy are added to the local ones
+    #[default]
+    Extend,
+    /// Lists in the policy replace the local ones
+    Override,
+}
+
+/// JSON served at `policy_url`, signed with minisign (`minisign -Sm policy.json`)
+/// and the signature served next to it at `<policy_url>.minisig`. Omitted
+/// lists leave the local config alone.
+#[derive(Deserialize)]
+struct RemotePolicy {
+    #[serde(default)]
+    mode: PolicyMode,
+    blocked_combos: Option<Vec<KeyCombo>>,
+    blocked_processes: Option<Vec<String>>,
+    allowed_origins: Option<Vec<String>>,
+    /// Replaces `security.disallow_safe_mode` whatever the mode
+    disallow_safe_mode: Option<bool>,
+}
+
+impl RemotePolicy {
+    fn apply(self, config: &mut LockdownConfig) {
+        fn merge<T>(local: &mut Vec<T>, policy: Option<Vec<T>>, mode: PolicyMode) {
+            match (policy, mode) {
+                (None, _) => {}
+                (Some(items), PolicyMode::Extend) => local.extend(items),
+                (Some(items), PolicyMode::Override) => *local = items,
+            }
+        }
+        merge(
+            &mut config.keyboard.blocked_combos,
+            self.blocked_combos,
+            self.mode,
+        );
+        merge(
+            &mut config.security.blocked_processes,
+            self.blocked_processes,
+            self.mode,
+        );
+        merge(&mut config.allowed_origins, self.allowed_origins, self.mode);
+        if let Some(disallow) = self.disallow_safe_mode {
+            config.security.disallow_safe_mode = disallow;
+        }
+    }
+}
+
+/// Fetch the policy at `url` and its signature, and parse it only once the
+/// signature checks out against `POLICY_PUBKEY`
+async fn fetch_policy(url: &str) -> Result<RemotePolicy, String> {
+    let public_key = POLICY_PUBKEY
+        .filter(|key| !key.is_empty())
+        .ok_or("this build has no policy public key")?;
+    let public_key = PublicKey::from_base64(public_key)
+        .map_err(|error| format!("invalid policy public key: {error}"))?;
+
+    let client = reqwest::Client::builder()
+        .timeout(POLICY_TIMEOUT)
+        .build()
+        .map_err(|error| error.to_string())?;
+    let get = |url: String| {
+        let client = client.clone();
+        async move {
+            client
+                .get(&url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+        }
+    };
+    let body = get(url.to_owned())
+        .await
+        .map_err(|error| format!("fetching policy: {error}"))?;
+    let signature = get(format!("{url}.minisig"))
+        .await
+        .map_err(|error| format!("fetching signature: {error}"))?;
+
+    let signature = Signature::decode(&String::from_utf8_lossy(&signature))
+        .map_err(|error| format!("invalid signature: {error}"))?;
+    public_key
+        .verify(&body, &signature, false)
+        .map_err(|error| format!("signature rejected: {error}"))?;
+    serde_json::from_slice(&body).map_err(|error| format!("invalid policy: {error}"))
+}
+
+// ============================================================================
+// Startup Environment Check
+// ============================================================================
+
+/// Why the app must not start on this machine, if anything: a blocklisted
+/// recorder or remote-control process is running, the app was launched
+/// inside a Remote Desktop session, or (when configured) inside a virtual
+/// machine.
+#[cfg(target_os = "windows")]
+fn startup_environment_error(security: &SecurityConfig) -> Option<String> {
+    if windows_security::is_remote_session() {
+        return Some(message("remote-session", &[]));
+    }
+
+    if security.block_virtual_machines {
+        if let Some(artifact) = windows_security::virtual_machine_artifact() {
+            log::warn!("virtual machine detected: {artifact}");
+            return Some(message("virtual-machine", &[]));
+        }
+    }
+
+    let mut running: Vec<String> = windows_security::running_process_names()
+        .into_iter()
+        .filter(|name| security.is_blocked(name))
+        .collect();
+    running.sort();
+    running.dedup();
+    if running.is_empty() {
+        return None;
+    }
+
+    Some(message(
+        "blocked-processes",
+        &[("programs", &running.join("\n"))],
+    ))
+}
+
+// ============================================================================
+// Process Watcher
+// ============================================================================
+
+#[cfg(target_os = "windows")]
+#[derive(Clone, Serialize)]
+struct ForbiddenProcessPayload {
+    name: String,
+}
+
+/// Stop signal and handle for the running process watcher, taken by
+/// `exit_lockdown`. Dropping the sender wakes the watcher immediately.
+#[cfg(target_os = "windows")]
+static PROCESS_WATCHER: Mutex<Option<(Sender<()>, JoinHandle<()>)>> = Mutex::new(None);
+
+/// Start re-scanning for blocklisted processes, since the startup check alone
+/// is defeated by launching the helper after the quiz loads.
+#[cfg(target_os = "windows")]
+fn start_process_watcher(app: AppHandle, security: SecurityConfig) {
+    let (stop, stopped) = mpsc::channel();
+    let handle = thread::spawn(move || watch_processes(app, security, stopped));
+    *PROCESS_WATCHER.lock().unwrap() = Some((stop, handle));
+}
+
+/// Signal the process watcher to stop and wait for it
+#[cfg(target_os = "windows")]
+fn stop_process_watcher() {
+    let watcher = PROCESS_WATCHER.lock().unwrap().take();
+    if let Some((stop, handle)) = watcher {
+        drop(stop);
+        let _ = handle.join();
+    }
+}
+
+/// Emit `forbidden-process` once for each blocklisted process that stays up
+/// for `process_grace_scans` consecutive scans. A process that exits and
+/// comes back is reported again.
+#[cfg(target_os = "windows")]
+fn watch_processes(app: AppHandle, security: SecurityConfig, stopped: Receiver<()>) {
+    let interval = Duration::from_secs(security.process_scan_interval_secs.max(1));
+    let grace = security.process_grace_scans.max(1);
+    // Consecutive scans each blocklisted process has been seen in
+    let mut seen: HashMap<String, u32> = HashMap::new();
+
+    while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+        let running: HashSet<String> = windows_security::running_process_names()
+            .into_iter()
+            .filter(|name| security.is_blocked(name))
+            .map(|name| name.to_ascii_lowercase())
+            .collect();
+        seen.retain(|name, _| running.contains(name));
+
+        for name in running {
+            let scans = seen.entry(name.clone()).or_insert(0);
+            *scans += 1;
+            if *scans == grace {
+                log::warn!("forbidden process running: {name}");
+                emit_violation(&app, "forbidden-process", ForbiddenProcessPayload { name });
+            }
+        }
+    }
+}
+
+/// Keep the main window above other topmost windows (toast notifications,
+/// tray popups) for the life of the app. Skipped while any of our own windows
+/// is open alongside it, so the loading recovery sidebar and its confirmation
+/// are never pushed under the quiz. UAC prompts run on the secure desktop and
+/// can't be covered by any app; Windows has no public API to turn on Focus
+/// Assist, so notifications are covered rather than suppressed.
+#[cfg(target_os = "windows")]
+fn keep_topmost(app: AppHandle, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        let windows = app.webview_windows();
+        if windows
+            .keys()
+            .filter(|label| !is_cover_window(label))
+            .count()
+            > 1
+        {
+            continue;
+        }
+        if TOPMOST_SUSPENDED.load(Ordering::SeqCst) {
+            continue;
+        }
+        if let Some(window) = windows.get("main") {
+            windows_security::raise_topmost(window);
+        }
+    }
+}
+
+/// How often `watch_touch_keyboard` looks for the touch keyboard
+#[cfg(target_os = "windows")]
+const TOUCH_KEYBOARD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Put the quiz back on top and focused whenever the touch keyboard appears
+/// despite `window.suppress_touch_keyboard`, so its settings and undock
+/// buttons can't be used to leave the quiz. Runs on its own thread for the
+/// life of the app.
+#[cfg(target_os = "windows")]
+fn watch_touch_keyboard(app: AppHandle) {
+    let mut visible = false;
+    loop {
+        thread::sleep(TOUCH_KEYBOARD_POLL_INTERVAL);
+        let now_visible = windows_security::touch_keyboard_visible();
+        if now_visible && !visible {
+            log::warn!("touch keyboard appeared; reasserting the quiz window");
+            log_event("touch-keyboard-shown", &serde_json::json!({}));
+            if let Some(window) = app.get_webview_window("main") {
+                windows_security::raise_topmost(&window);
+                let _ = window.set_focus();
+            }
+        }
+        visible = now_visible;
+    }
+}
+
+/// Empty the clipboard every `interval` for the life of the app
+#[cfg(target_os = "windows")]
+fn clear_clipboard_periodically(interval: Duration) {
+    loop {
+        thread::sleep(interval);
+        windows_security::clear_clipboard();
+    }
+}
+
+// ============================================================================
+// Idle Detection
+// ============================================================================
+
+/// How often the last-input time is checked
+#[cfg(target_os = "windows")]
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How close (ms) the system's last-input tick must be to a suppressed key's
+/// event time for that input to be attributed to the suppressed key
+#[cfg(target_os = "windows")]
+const BLOCKED_INPUT_SLACK_MS: u32 = 10;
+
+#[cfg(target_os = "windows")]
+#[derive(Clone, Serialize)]
+struct IdlePayload {
+    idle_secs: u64,
+}
+
+/// Emit `idle-warning` and then `idle-timeout` once the student has given no
+/// input for the configured times; both re-arm when input resumes. Runs on
+/// its own thread for the life of the app.
+///
+/// GetLastInputInfo also counts key presses the keyboard hook suppressed and
+/// the hook watchdog's injected self-test keystrokes, so a student leaning on
+/// Alt+Tab (or a stuck key repeating, or the watchdog itself) would look active.
+/// Input whose tick matches the last suppressed key is therefore ignored and
+/// the idle clock keeps running from the last genuine input.
+#[cfg(target_os = "windows")]
+fn watch_idle(app: AppHandle, idle: IdleConfig) {
+    let warning_ms = idle.warning_minutes.saturating_mul(60_000);
+    let timeout_ms = idle.timeout_minutes.saturating_mul(60_000);
+    if warning_ms == 0 && timeout_ms == 0 {
+        return;
+    }
+
+    let mut seen_tick = windows_security::last_input_tick().unwrap_or(0);
+    let mut genuine_tick = windows_security::tick_count();
+    let mut warned = false;
+    let mut timed_out = false;
+
+    loop {
+        thread::sleep(IDLE_POLL_INTERVAL);
+
+        if let Some(tick) = windows_security::last_input_tick() {
+            if tick != seen_tick {
+                seen_tick = tick;
+                let blocked = windows_security::last_blocked_tick();
+                let suppressed = tick.wrapping_sub(blocked) <= BLOCKED_INPUT_SLACK_MS
+                    || blocked.wrapping_sub(tick) <= BLOCKED_INPUT_SLACK_MS;
+                if !suppressed {
+                    genuine_tick = tick;
+                    warned = false;
+                    timed_out = false;
+                }
+            }
+        }
+
+        let idle_ms = u64::from(windows_security::tick_count().wrapping_sub(genuine_tick));
+        let idle_secs = idle_ms / 1000;
+        if warning_ms > 0 && !warned && idle_ms >= warning_ms {
+            warned = true;
+            emit_violation(&app, "idle-warning", IdlePayload { idle_secs });
+        }
+        if timeout_ms > 0 && !timed_out && idle_ms >= timeout_ms {
+            timed_out = true;
+            emit_violation(&app, "idle-timeout", IdlePayload { idle_secs });
+        }
+    }
+}
+
+/// Injected pointer moves within SYNTHETIC_INPUT_WINDOW that count as a
+/// program driving the mouse
+#[cfg(target_os = "windows")]
+const INJECTED_MOVES_THRESHOLD: usize = 5;
+
+/// Window for INJECTED_MOVES_THRESHOLD
+#[cfg(target_os = "windows")]
+const SYNTHETIC_INPUT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Largest move, in pixels, that counts as a nudge rather than real pointing
+#[cfg(target_os = "windows")]
+const NUDGE_MAX_PX: i32 = 3;
+
+/// Moves this close together (ms) are the same nudge, e.g. a jiggler's step
+/// out and back
+#[cfg(target_os = "windows")]
+const NUDGE_CLUSTER_MS: u32 = 200;
+
+/// Consecutive evenly spaced nudges that count as a jiggler
+#[cfg(target_os = "windows")]
+const REGULAR_NUDGES: usize = 8;
+
+/// How far (ms) a gap between nudges may stray from their average and still
+/// count as evenly spaced
+#[cfg(target_os = "windows")]
+const NUDGE_JITTER_MS: u64 = 50;
+
+/// Minimum time between two `synthetic-input-suspected` with the same reason
+#[cfg(target_os = "windows")]
+const SYNTHETIC_INPUT_COOLDOWN: Duration = Duration::from_secs(300);
+
+#[cfg(target_os = "windows")]
+#[derive(Clone, Serialize)]
+struct SyntheticInputPayload {
+    /// "injected" or "regular-movement"
+    reason: &'static str,
+}
+
+/// Whether `nudges` (event times) are REGULAR_NUDGES apart by roughly the
+/// same gap, as no hand resting on a mouse produces
+#[cfg(target_os = "windows")]
+fn evenly_spaced(nudges: &VecDeque<u32>) -> bool {
+    if nudges.len() < REGULAR_NUDGES {
+        return false;
+    }
+    let gaps: Vec<u64> = nudges
+        .iter()
+        .zip(nudges.iter().skip(1))
+        .map(|(a, b)| u64::from(b.wrapping_sub(*a)))
+        .collect();
+    let average = gaps.iter().sum::<u64>() / gaps.len() as u64;
+    gaps.iter()
+        .all(|gap| gap.abs_diff(average) <= NUDGE_JITTER_MS)
+}
+
+/// Emit `synthetic-input-suspected` when the mouse looks driven by a program
+/// (a "mouse jiggler" keeping the idle timer from firing): injected moves, or
+/// tiny moves at a fixed interval with no real pointing in between. Eye
+/// trackers, head mice and remote-assistance tools inject input too, so this
+/// can be switched off with `idle.detect_synthetic_input`. Runs on its own
+/// thread for the life of the app.
+#[cfg(target_os = "windows")]
+fn watch_synthetic_input(app: AppHandle) {
+    let Some(moves) = windows_security::subscribe_mouse_moves() else {
+        log::warn!("synthetic input detection unavailable");
+        return;
+    };
+    let mut injected = VecDeque::new();
+    let mut nudges = VecDeque::new();
+    let mut last_position: Option<(i32, i32)> = None;
+    let mut last_nudge_at = 0u32;
+    let mut last_reported: HashMap<&'static str, Instant> = HashMap::new();
+    let mut report = |reason: &'static str| {
+        let due = last_reported
+            .get(reason)
+            .is_none_or(|at| at.elapsed() >= SYNTHETIC_INPUT_COOLDOWN);
+        if due {
+            last_reported.insert(reason, Instant::now());
+            log::warn!("synthetic input suspected: {reason}");
+            emit_violation(
+                &app,
+                "synthetic-input-suspected",
+                SyntheticInputPayload { reason },
+            );
+        }
+    };
+
+    for event in moves {
+        if event.injected {
+            let now = Instant::now();
+            injected.push_back(now);
+            while injected
+                .front()
+                .is_some_and(|at| now.duration_since(*at) > SYNTHETIC_INPUT_WINDOW)
+            {
+                injected.pop_front();
+            }
+            if injected.len() >= INJECTED_MOVES_THRESHOLD {
+                injected.clear();
+                report("injected");
+            }
+        }
+
+        let nudge = last_position.is_some_and(|(x, y)| {
+            (event.x - x).abs() <= NUDGE_MAX_PX && (event.y - y).abs() <= NUDGE_MAX_PX
+        });
+        last_position = Some((event.x, event.y));
+        if !nudge {
+            nudges.clear();
+            continue;
+        }
+        let same_nudge = event.time.wrapping_sub(last_nudge_at) <= NUDGE_CLUSTER_MS;
+        last_nudge_at = event.time;
+        if same_nudge && !nudges.is_empty() {
+            continue;
+        }
+        nudges.push_back(event.time);
+        if nudges.len() > REGULAR_NUDGES {
+            nudges.pop_front();
+        }
+        if evenly_spaced(&nudges) {
+            nudges.clear();
+            report("regular-movement");
+        }
+    }
+}
+
+// ============================================================================
+// Focus Tracking
+// ============================================================================
+
+/// How long to wait after the main window blurs before checking where focus
+/// went, so one of our own windows has time to become focused
+const FOCUS_SETTLE_DELAY: Duration = Duration::from_millis(150);
+
+/// Focus losses counted since launch
+static FOCUS_LOSSES: AtomicU32 = AtomicU32::new(0);
+
+/// Shortest gap between `refocus_on_blur` attempts, capping them at four a
+/// second
+const REFOCUS_MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A loss this soon after a refocus attempt means the attempt didn't hold
+const REFOCUS_HOLD_TIME: Duration = Duration::from_secs(2);
+
+/// Refocus attempts in a row that didn't hold before giving up
+const MAX_REFOCUS_FAILURES: u32 = 10;
+
+struct RefocusState {
+    /// When the last attempt ran, or is scheduled to
+    last_attempt: Option<Instant>,
+    /// Attempts in a row that didn't hold
+    failures: u32,
+    gave_up: bool,
+}
+
+static REFOCUS: Mutex<RefocusState> = Mutex::new(RefocusState {
+    last_attempt: None,
+    failures: 0,
+    gave_up: false,
+});
+
+#[derive(Clone, Serialize)]
+struct FocusRecoveryFailedPayload {
+    attempts: u32,
+}
+
+/// Id of the element `set_content_hidden` adds to the page
+const BLUR_OVERLAY_ID: &str = "__understandly_lockdown_blur__";
+
+/// Cover the page with an opaque blurred overlay, or remove it. Applied the
+/// moment the main window blurs, before `track_focus_loss` knows whether
+/// focus went to another app, so a helper watching a screen share never
+/// sees the question.
+fn set_content_hidden(window: &tauri::WebviewWindow, hidden: bool) {
+    let script = if hidden {
+        format!(
+            r#"(() => {{
+                if (document.getElementById('{BLUR_OVERLAY_ID}')) return;
+                const overlay = document.createElement('div');
+                overlay.id = '{BLUR_OVERLAY_ID}';
+                overlay.style.cssText = 'position:fixed;inset:0;z-index:2147483647;' +
+                    'background:rgba(128,128,128,0.85);backdrop-filter:blur(24px);' +
+                    '-webkit-backdrop-filter:blur(24px)';
+                document.documentElement.appendChild(overlay);
+            }})();"#
+        )
+    } else {
+        format!("document.getElementById('{BLUR_OVERLAY_ID}')?.remove();")
+    };
+    if let Err(error) = window.eval(script) {
+        log::warn!("failed to update the blur overlay: {error}");
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct FocusLostPayload {
+    count: u32,
+    /// Windows: title of the window that took focus, capped at 200 characters
+    window_title: Option<String>,
+    /// Windows: executable name of the app that took focus
+    process: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct LockdownViolationPayload {
+    reason: &'static str,
+    count: u32,
+}
+
+#[derive(Clone, Serialize)]
+struct FileDropPayload {
+    /// Files in the drop; their names and contents are not reported
+    count: usize,
+}
+
+/// Count a focus loss of the main window and emit `focus-lost`, then
+/// `lockdown-violation` once the configured limit is reached. Focus moving to
+/// one of our own windows (the loading recovery sidebar and its confirmation
+/// dialog) is not counted, and lifts the overlay `hide_content` put up.
+fn track_focus_loss(app: AppHandle, max_focus_losses: u32, refocus: bool, hide_content: bool) {
+    thread::sleep(FOCUS_SETTLE_DELAY);
+
+    let windows = app.webview_windows();
+    if windows
+        .values()
+        .any(|window| window.is_focused().unwrap_or(false))
+    {
+        if hide_content {
+            if let Some(window) = windows.get("main") {
+                set_content_hidden(window, false);
+            }
+        }
+        return;
+    }
+
+    // Something that keeps taking focus back (e.g. a security prompt) would
+    // otherwise count as a new loss on every round of the fight
+    if refocus && refocus_failed() {
+        refocus_main(&app);
+        return;
+    }
+
+    #[cfg(target_os = "windows")]
+    let (window_title, process) = windows_security::foreground_window();
+    #[cfg(not(target_os = "windows"))]
+    let (window_title, process) = (None, None);
+
+    let count = FOCUS_LOSSES.fetch_add(1, Ordering::Relaxed) + 1;
+    log::info!(
+        "main window lost focus ({count}) to {:?} ({})",
+        window_title.as_deref().unwrap_or_default(),
+        process.as_deref().unwrap_or("unknown process")
+    );
+    emit_violation(
+        &app,
+        "focus-lost",
+        FocusLostPayload {
+            count,
+            window_title,
+            process,
+        },
+    );
+    if count == max_focus_losses {
+        emit_violation(
+            &app,
+            "lockdown-violation",
+            LockdownViolationPayload {
+                reason: "focus-lost",
+                count,
+            },
+        );
+    }
+
+    if refocus {
+        refocus_main(&app);
+    }
+}
+
+/// Whether this loss came within REFOCUS_HOLD_TIME of a refocus attempt,
+/// i.e. the attempt didn't hold
+fn refocus_failed() -> bool {
+    let mut state = REFOCUS.lock().unwrap();
+    let failed = state
+        .last_attempt
+        .is_some_and(|at| at.elapsed() < REFOCUS_HOLD_TIME);
+    state.failures = if failed { state.failures + 1 } else { 0 };
+    failed
+}
+
+/// Take focus back, at most once per REFOCUS_MIN_INTERVAL. After
+/// MAX_REFOCUS_FAILURES attempts in a row that didn't hold, emit
+/// `focus-recovery-failed` and stop trying until the window is focused some
+/// other way.
+fn refocus_main(app: &AppHandle) {
+    let wait = {
+        let mut state = REFOCUS.lock().unwrap();
+        if state.gave_up {
+            return;
+        }
+        if state.failures >= MAX_REFOCUS_FAILURES {
+            state.gave_up = true;
+            let attempts = state.failures;
+            drop(state);
+            log::warn!("giving up refocusing after {attempts} attempts that didn't hold");
+            emit_violation(
+                app,
+                "focus-recovery-failed",
+                FocusRecoveryFailedPayload { attempts },
+            );
+            return;
+        }
+        let now = Instant::now();
+        let wait = state
+            .last_attempt
+            .map(|at| (at + REFOCUS_MIN_INTERVAL).saturating_duration_since(now))
+            .unwrap_or_default();
+        state.last_attempt = Some(now + wait);
+        wait
+    };
+    thread::sleep(wait);
+    if let Some(window) = app.get_webview_window("main") {
+        if !window.is_focused().unwrap_or(false) {
+            let _ = window.set_focus();
+        }
+    }
+}
+
+/// Let `refocus_main` try again once the main window has focus back on its
+/// own
+fn focus_regained() {
+    let mut state = REFOCUS.lock().unwrap();
+    if state.gave_up {
+        state.gave_up = false;
+        state.failures = 0;
+    }
+}
+
+// ============================================================================
+// Monitor Watcher
+// ============================================================================
+
+/// How often the display count is re-read while the app runs
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Whether `monitor-changed` is emitted; switched by `set_lockdown_stage`
+static MONITOR_ENFORCEMENT: AtomicBool = AtomicBool::new(true);
+
+#[derive(Clone, Serialize)]
+struct MonitorChangedPayload {
+    previous: usize,
+    current: usize,
+}
+
+/// Emit `monitor-changed` whenever the display count changes, so a display
+/// hot-plugged after the quiz loads is caught without the page polling
+/// `get_monitor_count`. Runs on its own thread for the life of the app.
+fn watch_monitors(app: AppHandle) {
+    let mut previous = monitor_count(&app);
+
+    loop {
+        thread::sleep(MONITOR_POLL_INTERVAL);
+
+        // A failed read keeps the last known count rather than reporting a change
+        let Some(current) = monitor_count(&app) else {
+            continue;
+        };
+        let enforced = MONITOR_ENFORCEMENT.load(Ordering::SeqCst);
+        if let Some(previous) = previous.filter(|&previous| enforced && previous != current) {
+            log::info!("monitor count changed: {previous} -> {current}");
+            emit_violation(
+                &app,
+                "monitor-changed",
+                MonitorChangedPayload { previous, current },
+            );
+        }
+        previous = Some(current);
+    }
+}
+
+// ============================================================================
+// Input Device Watcher
+// ============================================================================
+
+/// How often the keyboards and mice are re-counted while the app runs
+#[cfg(target_os = "windows")]
+const INPUT_DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Physical keyboards and mice attached, see `windows_security::input_devices`
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct InputDevices {
+    keyboards: usize,
+    mice: usize,
+}
+
+#[cfg(target_os = "windows")]
+#[derive(Clone, Serialize)]
+struct InputDeviceChangedPayload {
+    previous: InputDevices,
+    current: InputDevices,
+}
+
+/// Emit `input-device-changed` when more keyboards or mice are attached than
+/// at the last check, e.g. a second keyboard handed to someone else. A
+/// device being unplugged only lowers the baseline, so plugging it back in
+/// is reported again. Runs on its own thread for the life of the app.
+#[cfg(target_os = "windows")]
+fn watch_input_devices(app: AppHandle) {
+    let mut previous = windows_security::input_devices();
+
+    loop {
+        thread::sleep(INPUT_DEVICE_POLL_INTERVAL);
+
+        // A failed read keeps the last known counts rather than reporting a change
+        let Some(current) = windows_security::input_devices() else {
+            continue;
+        };
+        if let Some(previous) = previous.filter(|previous| {
+            current.keyboards > previous.keyboards || current.mice > previous.mice
+        }) {
+            log::warn!(
+                "input devices added: {} -> {} keyboards, {} -> {} mice",
+                previous.keyboards,
+                current.keyboards,
+                previous.mice,
+                current.mice
+            );
+            emit_violation(
+                &app,
+                "input-device-changed",
+                InputDeviceChangedPayload { previous, current },
+            );
+        }
+        previous = Some(current);
+    }
+}
+
+// ============================================================================
+// Power Watcher
+// ============================================================================
+
+/// How often the battery is checked while the app runs
+#[cfg(target_os = "windows")]
+const POWER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Serialize)]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct PowerStatus {
+    /// Battery charge, or None without a battery or when unknown
+    percent: Option<u8>,
+    /// On AC power, whether or not the battery is still filling up
+    charging: bool,
+    /// Estimated battery time left, or None while charging or unknown
+    seconds_remaining: Option<u32>,
+}
+
+/// Battery charge and whether the machine is plugged in, e.g. for a pre-exam
+/// check that asks students to plug in
+#[tauri::command]
+fn get_power_status() -> Result<PowerStatus, String> {
+    #[cfg(target_os = "windows")]
+    return windows_security::power_status().ok_or_else(|| "power status is unavailable".into());
+
+    #[cfg(not(target_os = "windows"))]
+    Err("power status is only available on Windows".into())
+}
+
+/// Emit `low-battery` when the charge drops below `low_battery_percent`
+/// while unplugged, so the page can ask the student to plug in and save
+/// their answers. Fires once until the machine is plugged in or the charge
+/// is back above the threshold. Runs on its own thread for the life of the
+/// app.
+#[cfg(target_os = "windows")]
+fn watch_power(app: AppHandle, low_battery_percent: u8) {
+    let mut warned = false;
+    loop {
+        if let Some(status) = windows_security::power_status() {
+            let low = !status.charging
+                && status
+                    .percent
+                    .is_some_and(|percent| percent < low_battery_percent);
+            if low && !warned {
+                log::warn!("battery low: {:?}%", status.percent);
+                emit_violation(&app, "low-battery", status);
+            }
+            warned = low;
+        }
+        thread::sleep(POWER_POLL_INTERVAL);
+    }
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Label prefix of the black windows covering secondary monitors, see
+/// `window.cover_secondary_monitors`
+const COVER_WINDOW_PREFIX: &str = "cover-";
+
+fn is_cover_window(label: &str) -> bool {
+    label.starts_with(COVER_WINDOW_PREFIX)
+}
+
+/// Whether the platform keyboard blocker is currently installed
+fn keyboard_hook_active() -> bool {
+    #[cfg(target_os = "windows")]
+    return windows_security::is_hook_active();
+
+    #[cfg(target_os = "macos")]
+    return macos_security::is_hook_active();
+
+    #[cfg(target_os = "linux")]
+    return linux_security::is_hook_active();
+}
+
+/// Lowercase hex encoding of `bytes`
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Process start, the zero point for event timestamps
+static APP_STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Milliseconds from app start to `at`, for event payloads
+#[cfg(target_os = "windows")]
+fn monotonic_ms(at: Instant) -> u64 {
+    let started = *APP_STARTED.get_or_init(Instant::now);
+    at.saturating_duration_since(started).as_millis() as u64
+}
+
+#[derive(Clone, Copy, Serialize)]
+struct MonitorCounts {
+    /// Every display the OS reports
+    raw: usize,
+    /// Distinct desktop regions: displays sharing an origin, as in duplicate
+    /// (mirror) mode, count once
+    distinct: usize,
+}
+
+/// Distinct desktop regions, which is what every check and the
+/// `monitor-changed` event count: a projector duplicating the laptop screen
+/// shows nothing new, while an extended display does
+fn monitor_count(app: &AppHandle) -> Option<usize> {
+    monitor_counts(app).map(|counts| counts.distinct)
+}
+
+/// Connected displays from the platform module where one exists, so every
+/// monitor command agrees on every OS.
+fn monitor_counts(app: &AppHandle) -> Option<MonitorCounts> {
+    let origins = display_origins(app)?;
+    Some(MonitorCounts {
+        raw: origins.len(),
+        distinct: origins.iter().collect::<HashSet<_>>().len(),
+    })
+}
+
+/// Top-left corner of each connected display
+fn display_origins(app: &AppHandle) -> Option<Vec<(i32, i32)>> {
+    #[cfg(target_os = "macos")]
+    if let Some(origins) = macos_security::active_display_origins() {
+        return Some(origins);
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(origins) = linux_security::active_display_origins() {
+        return Some(origins);
+    }
+
+    app.available_monitors().ok().map(|monitors| {
+        monitors
+            .iter()
+            .map(|monitor| (monitor.position().x, monitor.position().y))
+            .collect()
+    })
+}
+
+/// The URL scheme registered under `plugins.deep-link` in tauri.conf.json,
+/// used when `deep_link_schemes` is empty
+const DEEP_LINK_SCHEME: &str = "understandly-lockdown";
+
+/// Where deep links are routed. Also managed as state for `resolve_deeplink`.
+#[derive(Clone)]
+struct DeepLinkRoutes {
+    base: String,
+    allowed_paths: Vec<String>,
+    /// Lowercase, as `Url::scheme` reports them
+    schemes: Vec<String>,
+}
 
-                // Block PrintScreen and F12 (DevTools)
-                if vk_code == VK_SNAPSHOT || vk_code == VK_F12 {
-                    return LRESULT(1);
-                }
+impl DeepLinkRoutes {
+    fn new(base: &str, config: &LockdownConfig) -> Self {
+        let schemes = if config.deep_link_schemes.is_empty() {
+            vec![DEEP_LINK_SCHEME.to_owned()]
+        } else {
+            config
+                .deep_link_schemes
+                .iter()
+                .map(|scheme| scheme.trim_end_matches("://").to_ascii_lowercase())
+                .collect()
+        };
+        Self {
+            base: base.to_owned(),
+            allowed_paths: config.allowed_deeplink_paths.clone(),
+            schemes,
+        }
+    }
 
-                // Block Ctrl+C, Ctrl+V, Ctrl+P
-                if ctrl_down && (vk_code == VK_C || vk_code == VK_V || vk_code == VK_P) {
-                    return LRESULT(1);
-                }
-            }
+    /// Where `link` would navigate, or why it would be rejected
+    fn resolve(&self, link: &Url) -> Result<Url, String> {
+        let target = to_local(link, &self.base, &self.schemes)?;
+        let path = deep_link_path(link);
+        if !deep_link_path_allowed(&path, &self.allowed_paths) {
+            return Err(format!("path {path:?} is not allowed"));
         }
+        Ok(target)
+    }
+}
 
-        // The hook handle is ignored by CallNextHookEx
-        CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+/// understandly-lockdown://quiz?x=1           →  <base>/quiz?x=1
+/// understandly-lockdown://results/987?y=true →  <base>/results/987?y=true
+/// understandly-lockdown:///quiz#q3           →  <base>/quiz#q3
+///
+/// The base may have a path (`https://host/exam-app`) and a query, which is
+/// kept ahead of the link's. Empty segments are dropped, so slashes are
+/// never doubled however the link is written (`understandly-lockdown:quiz`,
+/// `understandly-lockdown:////quiz/`).
+///
+/// Rejects links with a scheme not in `schemes`, and links whose composed
+/// target would leave the base origin or path once percent-decoded
+/// (`%2F%2Fhost`, `..` segments, backslashes).
+fn to_local(link: &Url, base: &str, schemes: &[String]) -> Result<Url, String> {
+    if !schemes.iter().any(|scheme| scheme == link.scheme()) {
+        return Err(format!("unexpected scheme {:?}", link.scheme()));
     }
 
-    /// Install the low-level keyboard hook
-    pub fn install_keyboard_hook() {
-        if HOOK_ACTIVE.swap(true, Ordering::SeqCst) {
-            return;
-        }
+    let base = Url::parse(base).map_err(|error| format!("invalid base URL: {error}"))?;
+    let base_path = base.path().trim_end_matches('/');
 
-        thread::spawn(|| unsafe {
-            let h_module = GetModuleHandleW(PCWSTR::null()).unwrap_or_default();
-            let h_instance = HINSTANCE(h_module.0);
+    let mut path = String::from(base_path);
+    for segment in [link.host_str().unwrap_or_default(), link.path()]
+        .into_iter()
+        .flat_map(|part| part.split('/'))
+        .filter(|segment| !segment.is_empty())
+    {
+        path.push('/');
+        path.push_str(segment);
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
 
-            let hook = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook_proc), h_instance, 0);
+    let query = match (base.query(), link.query()) {
+        (Some(base_query), Some(query)) => Some(format!("{base_query}&{query}")),
+        (base_query, query) => base_query.or(query).map(str::to_owned),
+    };
+    let mut target = base.clone();
+    target.set_path(&path);
+    target.set_query(query.as_deref());
+    target.set_fragment(link.fragment());
 
-            if let Ok(hook) = hook {
-                // Message loop to keep hook alive
-                let mut msg = MSG::default();
-                while GetMessageW(&mut msg, None, 0, 0).as_bool() {
-                    let _ = TranslateMessage(&msg);
-                    DispatchMessageW(&msg);
-                }
+    let inside_base = target.path() == base_path
+        || target
+            .path()
+            .strip_prefix(base_path)
+            .is_some_and(|rest| rest.starts_with('/'));
+    if target.origin() != base.origin() || !inside_base {
+        return Err(format!("{target} is outside {base}"));
+    }
 
-                // Cleanup on exit
-                let _ = UnhookWindowsHookEx(hook);
-            }
-            HOOK_ACTIVE.store(false, Ordering::SeqCst);
-        });
+    let decoded = percent_decode_str(target.path()).decode_utf8_lossy();
+    if decoded.contains("//")
+        || decoded.contains('\\')
+        || decoded.split('/').any(|segment| segment == "..")
+    {
+        return Err(format!("unsafe path {decoded:?}"));
     }
+
+    Ok(target)
 }
 
-// ============================================================================
-// macOS Security Module - Kiosk mode and screenshot protection
-// ============================================================================
+/// The path a deep link asks for, host included:
+/// `understandly-lockdown://results/987?y=true` → `results/987`
+fn deep_link_path(link: &Url) -> String {
+    [
+        link.host_str().unwrap_or_default().trim_matches('/'),
+        link.path().trim_matches('/'),
+    ]
+    .into_iter()
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join("/")
+}
 
-#[cfg(target_os = "macos")]
-mod macos_security {
-    use objc2::MainThreadMarker;
-    use objc2_app_kit::{
-        NSApplication, NSApplicationPresentationOptions, NSWindow, NSWindowSharingType,
-    };
+/// Whether `path` matches `allowed_deeplink_paths`: an entry ending in `*`
+/// matches every path starting with the rest of it, any other entry only that
+/// exact path. An empty list allows every path.
+fn deep_link_path_allowed(path: &str, allowed: &[String]) -> bool {
+    allowed.is_empty()
+        || allowed
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => path.starts_with(prefix.trim_start_matches('/')),
+                None => path == pattern.trim_matches('/'),
+            })
+}
 
-    /// Put the app into kiosk mode: hides the Dock and menu bar and disables
-    /// Cmd+Tab process switching and hiding the app. Force-quit and session
-    /// termination intentionally remain available as OS-level recovery paths.
-    /// Must be called on the main thread.
-    #[allow(unused_unsafe)]
-    pub fn enable_kiosk_mode() {
-        let Some(mtm) = MainThreadMarker::new() else {
-            eprintln!("[Lockdown] kiosk mode skipped: not on main thread");
-            return;
-        };
-        let app = NSApplication::sharedApplication(mtm);
-        unsafe {
-            app.setPresentationOptions(
-                NSApplicationPresentationOptions::HideDock
-                    | NSApplicationPresentationOptions::HideMenuBar
-                    | NSApplicationPresentationOptions::DisableProcessSwitching
-                    | NSApplicationPresentationOptions::DisableHideApplication,
-            );
-        }
+#[derive(Clone, Serialize)]
+struct DeepLinkRejectedPayload {
+    path: String,
+}
+
+/// The first link that `to_local` accepts and whose path is allowed, falling
+/// back to the base URL when every link is rejected. Later links are only
+/// consulted if earlier ones are rejected, so a malformed first URL doesn't
+/// strand the student. A path outside `allowed_paths` is reported as
+/// `deep-link-rejected`.
+fn deep_link_target(app: &AppHandle, links: &[Url], routes: &DeepLinkRoutes) -> Url {
+    if links.len() > 1 {
+        log::warn!(
+            "received {} deep links, using the first acceptable one",
+            links.len()
+        );
     }
 
-    /// Exclude the window from screenshots and screen recordings
-    /// (Cmd+Shift+3/4/5 capture a blank area where the window is).
-    #[allow(unused_unsafe)]
-    pub fn disable_window_capture(window: &tauri::WebviewWindow) {
-        if let Ok(ptr) = window.ns_window() {
-            unsafe {
-                let ns_window: &NSWindow = &*ptr.cast();
-                ns_window.setSharingType(NSWindowSharingType::None);
+    for link in links {
+        let target = match to_local(link, &routes.base, &routes.schemes) {
+            Ok(target) => target,
+            Err(error) => {
+                log::warn!("deep link rejected: {error}");
+                continue;
             }
+        };
+        let path = deep_link_path(link);
+        if deep_link_path_allowed(&path, &routes.allowed_paths) {
+            return target;
         }
+        log::warn!("deep link rejected: path {path:?} is not allowed");
+        emit_violation(app, "deep-link-rejected", DeepLinkRejectedPayload { path });
+    }
+
+    Url::parse(&routes.base).expect("base URL should be valid")
+}
+
+/// Where `url` would navigate if it arrived as a deep link, without
+/// navigating, e.g. so the page can confirm first. Errors with the reason a
+/// deep link would have been rejected; nothing is emitted or logged.
+#[tauri::command]
+fn resolve_deeplink(url: String, routes: State<'_, DeepLinkRoutes>) -> Result<String, String> {
+    let link = Url::parse(&url).map_err(|error| format!("invalid URL: {error}"))?;
+    routes.resolve(&link).map(String::from)
+}
+
+/// The configured navigation allowlist, or the base URL's origin when none is
+/// configured. Entries that don't parse as URLs are logged and skipped.
+fn allowed_origins(configured: &[String], base: &str) -> Vec<url::Origin> {
+    if configured.is_empty() {
+        return Url::parse(base)
+            .map(|url| vec![url.origin()])
+            .unwrap_or_default();
     }
+
+    configured
+        .iter()
+        .filter_map(|origin| match Url::parse(origin) {
+            Ok(url) => Some(url.origin()),
+            Err(error) => {
+                log::warn!("ignoring allowed origin {origin:?}: {error}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Whether the main window may load `url`. Deep links resolved by `to_local`
+/// stay on the base origin, so they always pass.
+fn navigation_allowed(url: &Url, allowed: &[url::Origin]) -> bool {
+    let origin = url.origin();
+    origin.is_tuple() && allowed.contains(&origin)
 }
 
 // ============================================================================
-// Tauri Commands
+// Network Allowlist
 // ============================================================================
 
-#[tauri::command]
-fn close_app(app: AppHandle) {
-    app.exit(0);
-}
+/// Hosts Tauri serves the app's own pages, assets and IPC from on Windows
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+const INTERNAL_HOSTS: [&str; 3] = ["tauri.localhost", "ipc.localhost", "asset.localhost"];
 
-#[tauri::command]
-fn close_lockdown(app: AppHandle) {
-    app.exit(0);
+/// Origins `network-request-blocked` has already been emitted for
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+static BLOCKED_REQUEST_ORIGINS: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+#[derive(Clone, Serialize)]
+struct RequestBlockedPayload {
+    origin: String,
 }
 
-#[tauri::command]
-fn close_during_loading(
-    app: AppHandle,
-    state: State<'_, Arc<QuizSessionState>>,
-) -> Result<(), String> {
-    match *state.phase.lock().unwrap() {
-        QuizPhase::Loading | QuizPhase::CheckingUpdate { .. } => {}
-        QuizPhase::Ready => {
-            return Err("the quiz is active; use the quiz close flow".into());
-        }
-        QuizPhase::Updating { .. } => {
-            return Err("an application update is being installed".into());
+/// Whether the main window may request `url` while `network_allowlist` is
+/// on: an `allowed` origin, the app's own pages and IPC, or inline data
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn request_allowed(url: &str, allowed: &[url::Origin]) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    match url.scheme() {
+        "data" | "blob" | "about" => true,
+        "http"
+            if url
+                .host_str()
+                .is_some_and(|host| INTERNAL_HOSTS.contains(&host)) =>
+        {
+            true
         }
+        _ => navigation_allowed(&url, allowed),
     }
+}
 
-    app.exit(0);
-    Ok(())
+/// Log a request `request_allowed` refused, and emit
+/// `network-request-blocked` the first time its origin is refused, since a
+/// page may retry the same request many times
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn report_blocked_request(app: &AppHandle, url: &str) {
+    let origin = Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| "null".into());
+    let shown: String = url.chars().take(200).collect();
+    log::warn!("request blocked: {shown}");
+    let first = BLOCKED_REQUEST_ORIGINS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashSet::new)
+        .insert(origin.clone());
+    if first {
+        emit_violation(
+            app,
+            "network-request-blocked",
+            RequestBlockedPayload { origin },
+        );
+    }
 }
 
-#[tauri::command]
-fn mark_quiz_ready(app: AppHandle, state: State<'_, Arc<QuizSessionState>>) -> Result<(), String> {
-    let close_recovery = {
-        let mut phase = state.phase.lock().unwrap();
-        match &mut *phase {
-            QuizPhase::CheckingUpdate { ready_requested }
-            | QuizPhase::Updating { ready_requested } => {
-                *ready_requested = true;
-                false
+/// The configured WebSocket origins; entries that aren't `ws:` or `wss:`
+/// URLs are logged and skipped
+fn websocket_origins(configured: &[String]) -> Vec<String> {
+    configured
+        .iter()
+        .filter_map(|origin| match Url::parse(origin) {
+            Ok(url) if matches!(url.scheme(), "ws" | "wss") => {
+                Some(url.origin().ascii_serialization())
             }
-            QuizPhase::Loading => {
-                *phase = QuizPhase::Ready;
-                true
+            _ => {
+                log::warn!("ignoring WebSocket origin {origin:?}: not a ws:// or wss:// URL");
+                None
             }
-            QuizPhase::Ready => true,
-        }
-    };
+        })
+        .collect()
+}
 
-    if close_recovery {
-        close_loading_recovery(&app)?;
+/// Makes `WebSocket` throw for any origin outside `origins`. WebView2 doesn't
+/// pass WebSockets to the request filter, so this page-level guard is all
+/// that covers them; the constructor can't be put back afterwards.
+fn websocket_guard_script(origins: &[String]) -> String {
+    let origins_json =
+        serde_json::to_string(origins).expect("WebSocket origins should serialize to JSON");
+    format!(
+        r#"
+        (function () {{
+            var allowed = {origins_json};
+            var NativeWebSocket = window.WebSocket;
+            var GuardedWebSocket = function WebSocket(url, protocols) {{
+                var origin = '';
+                try {{
+                    origin = new URL(url, location.href).origin;
+                }} catch (e) {{}}
+                if (allowed.indexOf(origin) === -1) {{
+                    throw new DOMException('WebSocket connections to ' + origin + ' are blocked', 'SecurityError');
+                }}
+                return arguments.length > 1
+                    ? new NativeWebSocket(url, protocols)
+                    : new NativeWebSocket(url);
+            }};
+            ['CONNECTING', 'OPEN', 'CLOSING', 'CLOSED'].forEach(function (name) {{
+                GuardedWebSocket[name] = NativeWebSocket[name];
+            }});
+            GuardedWebSocket.prototype = NativeWebSocket.prototype;
+            // Otherwise `new socket.constructor(url)` would reach the original
+            Object.defineProperty(NativeWebSocket.prototype, 'constructor', {{
+                value: GuardedWebSocket,
+                writable: false,
+                configurable: false
+            }});
+            Object.defineProperty(window, 'WebSocket', {{
+                value: GuardedWebSocket,
+                writable: false,
+                configurable: false
+            }});
+        }})();
+        "#
+    )
+}
+
+// ============================================================================
+// Offline Recovery
+// ============================================================================
+
+/// Bundled page shown in the main window when the quiz host can't be reached.
+/// Served from `frontendDist`, whose origin differs per platform.
+const OFFLINE_PAGE: &str = if cfg!(target_os = "windows") {
+    "http://tauri.localhost/offline.html"
+} else {
+    "tauri://localhost/offline.html"
+};
+
+/// How long to wait on each resolved address before calling the host down
+const REACHABILITY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where a retry navigates: the resolved entry URL until a load fails, then
+/// the page that failed
+static RETRY_URL: Mutex<Option<Url>> = Mutex::new(None);
+
+fn is_offline_page(url: &Url) -> bool {
+    url.as_str().split(['?', '#']).next() == Some(OFFLINE_PAGE)
+}
+
+/// Whether a TCP connection to the URL's host opens. The webview doesn't
+/// report failed loads, so this distinguishes an error page from a real one.
+fn host_reachable(url: &Url) -> bool {
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return false;
+    };
+    match (host, port).to_socket_addrs() {
+        Ok(addrs) => addrs
+            .into_iter()
+            .any(|addr| TcpStream::connect_timeout(&addr, REACHABILITY_TIMEOUT).is_ok()),
+        Err(_) => false,
     }
-    Ok(())
 }
 
-/// Check if multiple monitors are connected (for the frontend to react)
-#[tauri::command]
-fn check_multiple_monitors(app: AppHandle) -> bool {
-    app.available_monitors()
-        .map(|monitors| monitors.len() > 1)
-        .unwrap_or(false)
+/// Runs off the main thread after a quiz page finishes loading; swaps the
+/// browser's error page for the offline page when the host is unreachable.
+fn check_page_load(app: AppHandle, url: Url) {
+    if host_reachable(&url) {
+        return;
+    }
+
+    log::warn!("{url} unreachable; showing offline page");
+    *RETRY_URL.lock().unwrap() = Some(url);
+    if let Some(win) = app.get_webview_window("main") {
+        let _ = win.navigate(Url::parse(OFFLINE_PAGE).expect("offline page URL should be valid"));
+    }
 }
 
-/// Get monitor count
+/// Called by the offline page. Returns to the page that failed once its host
+/// answers again, and errors while it's still unreachable so the page keeps
+/// backing off.
 #[tauri::command]
-fn get_monitor_count(app: AppHandle) -> usize {
-    app.available_monitors()
-        .map(|monitors| monitors.len())
-        .unwrap_or(1)
+async fn reload_quiz(app: AppHandle) -> Result<(), String> {
+    let target = RETRY_URL
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("no quiz URL to reload")?;
+
+    let probe = target.clone();
+    let reachable = tauri::async_runtime::spawn_blocking(move || host_reachable(&probe))
+        .await
+        .map_err(|error| error.to_string())?;
+    if !reachable {
+        return Err(format!(
+            "{} is still unreachable",
+            target.origin().ascii_serialization()
+        ));
+    }
+
+    log::info!("{target} reachable again; reloading");
+    let win = app
+        .get_webview_window("main")
+        .ok_or("main window not found")?;
+    win.navigate(target).map_err(|error| error.to_string())
 }
 
 // ============================================================================
-// Auto-Updater
+// Crash Recovery
 // ============================================================================
 
-fn close_loading_recovery(app: &AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("loading-recovery") {
-        window.close().map_err(|error| error.to_string())?;
+/// Marker file in the app data directory, present while an exam is active
+const RECOVERY_FILE_NAME: &str = "session-recovery.json";
+
+/// How often the marker is refreshed with the main window's current URL
+const RECOVERY_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+static RECOVERY_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Id of this session, see `session_id`
+static SESSION_ID: OnceLock<String> = OnceLock::new();
+
+#[derive(Deserialize, Serialize)]
+struct RecoveryMarker {
+    session_id: String,
+    url: String,
+    /// Unix time in milliseconds
+    saved_ms: u64,
+}
+
+/// Identifies this session in the recovery marker and `SessionSummary`. A
+/// resumed session keeps the id of the one that crashed.
+fn session_id() -> &'static str {
+    SESSION_ID.get_or_init(|| format!("{:x}-{:x}", unix_time_ms(), std::process::id()))
+}
+
+/// The page to reopen if the marker in `dir` was saved under `max_age` ago
+/// and points somewhere the window may navigate. Any marker found is
+/// removed, so a resume that itself crashes before saving isn't repeated.
+fn load_recovery_marker(
+    dir: &Path,
+    max_age: Duration,
+    allowed: &[url::Origin],
+) -> Option<(Url, String)> {
+    let path = dir.join(RECOVERY_FILE_NAME);
+    let _ = RECOVERY_FILE.set(path.clone());
+    let contents = fs::read_to_string(&path).ok()?;
+    let _ = fs::remove_file(&path);
+
+    let marker: RecoveryMarker = match serde_json::from_str(&contents) {
+        Ok(marker) => marker,
+        Err(error) => {
+            log::warn!("ignoring unreadable recovery marker: {error}");
+            return None;
+        }
+    };
+    let age = Duration::from_millis((unix_time_ms() as u64).saturating_sub(marker.saved_ms));
+    if age > max_age {
+        log::info!("ignoring recovery marker from {}s ago", age.as_secs());
+        return None;
     }
-    if let Some(main_win) = app.get_webview_window("main") {
-        let _ = main_win.set_position(tauri::Position::Logical(tauri::LogicalPosition::new(
-            0.0, 0.0,
-        )));
-        main_win
-            .set_fullscreen(true)
-            .map_err(|error| error.to_string())?;
-        let _ = main_win.set_focus();
+    let url = Url::parse(&marker.url)
+        .ok()
+        .filter(|url| navigation_allowed(url, allowed))?;
+    Some((url, marker.session_id))
+}
+
+/// Write the main window's URL to the marker every RECOVERY_SAVE_INTERVAL
+/// while an exam is active, and remove it while none is. Runs on its own
+/// thread for the life of the app.
+fn keep_recovery_marker(app: AppHandle, state: Arc<QuizSessionState>, allowed: Vec<url::Origin>) {
+    let Some(path) = RECOVERY_FILE.get() else {
+        return;
+    };
+    loop {
+        thread::sleep(RECOVERY_SAVE_INTERVAL);
+        if !state.exam_active.load(Ordering::SeqCst) {
+            let _ = fs::remove_file(path);
+            continue;
+        }
+        // The offline page can't be resumed; keep the last quiz URL instead
+        let Some(url) = app
+            .get_webview_window("main")
+            .and_then(|window| window.url().ok())
+            .filter(|url| navigation_allowed(url, &allowed))
+        else {
+            continue;
+        };
+        let marker = RecoveryMarker {
+            session_id: session_id().into(),
+            url: url.into(),
+            saved_ms: unix_time_ms() as u64,
+        };
+        let json = serde_json::to_vec(&marker).expect("recovery marker should serialize");
+        if let Err(error) = fs::write(path, json) {
+            log::warn!("failed to save recovery marker: {error}");
+        }
     }
-    Ok(())
 }
 
-fn set_loading_recovery_updating(app: &AppHandle) {
-    if let Some(window) = app.get_webview_window("loading-recovery") {
-        let _ = window.eval("window.__UNDERSTANDLY_LOCKDOWN_UPDATING__?.();");
+/// Remove the marker, so the next launch starts fresh
+fn clear_recovery_marker() {
+    if let Some(path) = RECOVERY_FILE.get() {
+        let _ = fs::remove_file(path);
     }
 }
 
-fn restore_loading_recovery(app: &AppHandle) {
-    if let Some(window) = app.get_webview_window("loading-recovery") {
-        let _ = window.eval("window.__UNDERSTANDLY_LOCKDOWN_RESTORE_LOADING__?.();");
+// ============================================================================
+// View State
+// ============================================================================
+
+/// File in the app data directory holding the page's `save_view_state` blob
+const VIEW_STATE_FILE_NAME: &str = "view-state.json";
+
+/// Largest blob `save_view_state` accepts
+const MAX_VIEW_STATE_LEN: usize = 1024 * 1024;
+
+#[derive(Deserialize, Serialize)]
+struct ViewState {
+    session_id: String,
+    state: String,
+}
+
+fn view_state_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(VIEW_STATE_FILE_NAME))
+        .map_err(|error| error.to_string())
+}
+
+/// Store `state`, an opaque blob the page builds (scroll position, unsaved
+/// answers), for `restore_view_state` to hand back after a reload. It's kept
+/// on disk, so it also survives a crash the session is resumed from.
+#[tauri::command]
+fn save_view_state(app: AppHandle, state: String) -> Result<(), String> {
+    if state.len() > MAX_VIEW_STATE_LEN {
+        return Err(format!(
+            "view state must be at most {MAX_VIEW_STATE_LEN} bytes"
+        ));
     }
+    let path = view_state_path(&app)?;
+    let view_state = ViewState {
+        session_id: session_id().into(),
+        state,
+    };
+    let json = serde_json::to_vec(&view_state).expect("view state should serialize");
+    fs::write(&path, json).map_err(|error| format!("{}: {error}", path.display()))
 }
 
-fn finish_update_check(app: &AppHandle, state: &QuizSessionState) {
-    let ready_requested = {
-        let mut phase = state.phase.lock().unwrap();
-        match &*phase {
-            QuizPhase::CheckingUpdate { ready_requested }
-            | QuizPhase::Updating { ready_requested } => {
-                let requested = *ready_requested;
-                *phase = if requested {
-                    QuizPhase::Ready
-                } else {
-                    QuizPhase::Loading
-                };
-                requested
-            }
-            QuizPhase::Loading | QuizPhase::Ready => false,
+/// The blob last saved by `save_view_state` in this session, or `None` if
+/// there is none. State saved by another session is never returned.
+#[tauri::command]
+fn restore_view_state(app: AppHandle) -> Option<String> {
+    let contents = fs::read_to_string(view_state_path(&app).ok()?).ok()?;
+    let view_state: ViewState = serde_json::from_str(&contents).ok()?;
+    (view_state.session_id == session_id()).then_some(view_state.state)
+}
+
+/// Delete the saved view state, so answers don't outlive the session
+fn clear_view_state(app: &AppHandle) {
+    if let Ok(path) = view_state_path(app) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// ============================================================================
+// Browser Data Isolation
+// ============================================================================
+
+/// Directory in the app's local data dir holding one webview profile per
+/// session, named after `session_id`
+#[cfg(not(target_os = "macos"))]
+const BROWSER_DATA_DIR_NAME: &str = "webview-sessions";
+
+/// Set from `security.isolate_browser_data`, read by `exit_lockdown`
+static CLEAR_BROWSER_DATA_ON_EXIT: AtomicBool = AtomicBool::new(false);
+
+/// The profile directory for this session, after deleting those left by
+/// earlier ones. Deleting works here but not at exit, where the webview
+/// processes may still hold the files open. A resumed session keeps its
+/// `session_id` and so its profile, along with the SSO cookies in it.
+#[cfg(not(target_os = "macos"))]
+fn session_browser_data_dir(app: &AppHandle) -> Option<PathBuf> {
+    let root = match app.path().app_local_data_dir() {
+        Ok(dir) => dir.join(BROWSER_DATA_DIR_NAME),
+        Err(error) => {
+            log::warn!("no local data dir for the webview profile: {error}");
+            return None;
         }
     };
+    let current = root.join(session_id());
+    for entry in fs::read_dir(&root).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path != current {
+            if let Err(error) = fs::remove_dir_all(&path) {
+                log::warn!(
+                    "failed to delete old webview profile {}: {error}",
+                    path.display()
+                );
+            }
+        }
+    }
+    Some(current)
+}
+
+/// Delete the main window's cookies, cache and storage. `exit_lockdown`
+/// calls this when `security.isolate_browser_data` is on.
+#[tauri::command]
+fn clear_browser_data(app: AppHandle) -> Result<(), String> {
+    app.get_webview_window("main")
+        .ok_or("main window not found")?
+        .clear_all_browsing_data()
+        .map_err(|error| error.to_string())
+}
+
+// ============================================================================
+// Watchdog
+// ============================================================================
+
+/// Launch argument that runs this binary as the watchdog instead of the app
+const WATCHDOG_ARG: &str = "--watchdog";
+
+/// Set on the child so it knows to leave a clean-exit marker
+const WATCHDOG_CHILD_ENV: &str = "UNDERSTANDLY_LOCKDOWN_WATCHDOG_CHILD";
+
+/// Pause before relaunching, so an installer or the OS can finish with the
+/// old process first
+const WATCHDOG_RELAUNCH_DELAY: Duration = Duration::from_secs(2);
+
+/// A child that exits this soon after launch counts as a failed start
+const WATCHDOG_QUICK_EXIT: Duration = Duration::from_secs(10);
+
+/// Consecutive failed starts after which the watchdog gives up, e.g. when a
+/// second instance keeps exiting because the first is still running
+const WATCHDOG_MAX_QUICK_EXITS: u32 = 3;
+
+/// Whether this process was launched by the watchdog
+fn under_watchdog() -> bool {
+    std::env::var_os(WATCHDOG_CHILD_ENV).is_some()
+}
 
-    if ready_requested {
-        let _ = close_loading_recovery(app);
-    } else {
-        restore_loading_recovery(app);
-    }
+/// Written by the child on an intentional exit so the watchdog stops
+fn clean_exit_marker() -> PathBuf {
+    std::env::temp_dir().join("understandly-lockdown.clean-exit")
 }
 
-async fn check_for_updates(
-    app: AppHandle,
-    state: Arc<QuizSessionState>,
-) -> tauri_plugin_updater::Result<()> {
-    let check_result = app
-        .updater_builder()
-        // This timeout also covers the installer download. Keep it bounded so a
-        // failed update cannot hold the loading screen indefinitely, while
-        // allowing normal home connections enough time to fetch the bundle.
-        .timeout(Duration::from_secs(60))
-        .build()?
-        .check()
-        .await;
+/// Written by `restart_clean` so the relaunched app starts on the base URL
+fn restart_clean_marker() -> PathBuf {
+    std::env::temp_dir().join("understandly-lockdown.restart-clean")
+}
 
-    let update = match check_result {
-        Ok(Some(update)) => update,
-        Ok(None) => {
-            finish_update_check(&app, &state);
-            return Ok(());
+/// Whether this launch comes from `restart_clean`; removes the marker
+fn take_restart_clean_marker() -> bool {
+    fs::remove_file(restart_clean_marker()).is_ok()
+}
+
+/// Tell the watchdog, if any, that this exit is intentional
+fn mark_clean_exit() {
+    if under_watchdog() {
+        if let Err(error) = fs::write(clean_exit_marker(), b"") {
+            log::error!("failed to write clean-exit marker: {error}");
         }
+    }
+}
+
+/// Run the app as a child and relaunch it whenever it exits without leaving
+/// the clean-exit marker (`close_app`, `close_lockdown`, the emergency
+/// shortcut, or a refused start). Returns the exit code for this process.
+/// A student who can kill the app can also kill the watchdog; this only
+/// raises the bar from killing one process to noticing and killing two.
+fn run_watchdog() -> i32 {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
         Err(error) => {
-            finish_update_check(&app, &state);
-            return Err(error);
+            log::error!("watchdog can't find its executable: {error}");
+            return 1;
         }
     };
+    let args: Vec<_> = std::env::args_os()
+        .skip(1)
+        .filter(|arg| arg != WATCHDOG_ARG)
+        .collect();
+    let marker = clean_exit_marker();
+    let mut quick_exits = 0;
 
-    let install_update = {
-        let mut phase = state.phase.lock().unwrap();
-        match &*phase {
-            QuizPhase::CheckingUpdate { ready_requested } => {
-                let requested = *ready_requested;
-                *phase = QuizPhase::Updating {
-                    ready_requested: requested,
-                };
-                true
+    loop {
+        let _ = fs::remove_file(&marker);
+        let started = Instant::now();
+        let status = std::process::Command::new(&exe)
+            .args(&args)
+            .env(WATCHDOG_CHILD_ENV, "1")
+            .status();
+        let status = match status {
+            Ok(status) => status,
+            Err(error) => {
+                log::error!("watchdog failed to launch the app: {error}");
+                return 1;
             }
-            QuizPhase::Loading | QuizPhase::Ready | QuizPhase::Updating { .. } => false,
+        };
+
+        if fs::remove_file(&marker).is_ok() {
+            return status.code().unwrap_or(0);
         }
-    };
 
-    if !install_update {
-        println!(
-            "[Lockdown] update {} deferred because the quiz is active",
-            update.version
-        );
-        return Ok(());
+        if started.elapsed() < WATCHDOG_QUICK_EXIT {
+            quick_exits += 1;
+            if quick_exits >= WATCHDOG_MAX_QUICK_EXITS {
+                log::error!("watchdog giving up after {quick_exits} failed starts");
+                return status.code().unwrap_or(1);
+            }
+        } else {
+            quick_exits = 0;
+        }
+
+        log::warn!("app exited unexpectedly ({status}); relaunching");
+        thread::sleep(WATCHDOG_RELAUNCH_DELAY);
     }
+}
 
-    println!("[Lockdown] installing update {}", update.version);
-    set_loading_recovery_updating(&app);
+// ============================================================================
+// Startup Errors
+// ============================================================================
 
-    if let Err(error) = update.download_and_install(|_, _| {}, || {}).await {
-        finish_update_check(&app, &state);
-        return Err(error);
-    }
+/// Set once setup has finished; a panic before that is a failed start
+static STARTUP_COMPLETE: AtomicBool = AtomicBool::new(false);
 
-    println!("[Lockdown] update installed; restarting");
-    app.restart();
+/// Show `text` in an error dialog (Windows) with a reference the student
+/// can quote to IT, log it with the same reference, and exit 1. Release
+/// builds have no console, so without the dialog a failed start looks like
+/// the app doing nothing.
+fn startup_failed(text: &str) -> ! {
+    let unix_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let reference = format!("LD-{unix_secs:X}");
+    log::error!("startup failed [{reference}]: {text}");
+    let _dialog = format!(
+        "{text}\n\n{}",
+        message("error-reference", &[("reference", &reference)])
+    );
+    #[cfg(target_os = "windows")]
+    windows_security::show_error("Understandly Lockdown", &_dialog);
+    mark_clean_exit();
+    std::process::exit(1);
+}
+
+/// Log every panic, and turn one during startup into `startup_failed`
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        log::error!("panic: {info}");
+        if !STARTUP_COMPLETE.load(Ordering::SeqCst) {
+            startup_failed(&message("startup-failed", &[("error", &info.to_string())]));
+        }
+        default_hook(info);
+    }));
 }
 
 // ============================================================================
-// Helper Functions
+// Main Entry Point
 // ============================================================================
 
-/// understandly_lockdown://quiz?x=1           →  <base>/quiz?x=1
-/// understandly_lockdown://results/987?y=true →  <base>/results/987?y=true
-fn to_local(link: &Url, base: &str) -> String {
-    let mut target = String::from(base.trim_end_matches('/'));
-
-    if let Some(host) = link.host_str() {
-        if !host.is_empty() {
-            target.push('/');
-            target.push_str(host.trim_start_matches('/'));
+fn main() {
+    APP_STARTED.get_or_init(Instant::now);
+    init_logging();
+    install_panic_hook();
+    let cli = CliArgs::parse(std::env::args_os().skip(1));
+    let mut config = match LockdownConfig::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(error) => {
+            let _ = I18N.set(LockdownConfig::load_i18n(cli.config.as_deref()));
+            startup_failed(&message("config-invalid", &[("error", &error)]));
         }
+    };
+
+    let _ = I18N.set(mem::take(&mut config.i18n));
+    if config.alarm.enabled {
+        let _ = ALARM.set(mem::take(&mut config.alarm));
     }
+    let _ = WINDOW_MODE.set(config.window.mode);
+    CONFIGURED_ALWAYS_ON_TOP.store(config.window.always_on_top, Ordering::SeqCst);
+    ALWAYS_ON_TOP_RESTORE_SECS.store(config.window.always_on_top_restore_secs, Ordering::SeqCst);
 
-    let path = link.path().trim_start_matches('/');
-    if !path.is_empty() {
-        target.push('/');
-        target.push_str(path);
+    match config.debug_settings.log_level.parse::<log::LevelFilter>() {
+        Ok(level) => log::set_max_level(level),
+        Err(_) => log::warn!(
+            "unknown debug_settings.log_level {:?}; using info",
+            config.debug_settings.log_level
+        ),
+    }
+    if cli.debug {
+        log::set_max_level(log::LevelFilter::Debug);
+        log::info!("--debug: logging at debug level");
     }
 
-    if let Some(q) = link.query() {
-        target.push('?');
-        target.push_str(q);
+    if cli.watchdog {
+        if config.watchdog.enabled {
+            std::process::exit(run_watchdog());
+        }
+        log::warn!("{WATCHDOG_ARG} ignored: watchdog.enabled is off");
     }
 
-    target
-}
+    // Before the startup check and hook install, so the policy's lists apply
+    // to both
+    if let Some(url) = config.policy_url.clone() {
+        match tauri::async_runtime::block_on(fetch_policy(&url)) {
+            Ok(policy) => {
+                log::info!("applying policy from {url}");
+                policy.apply(&mut config);
+            }
+            Err(error) => log::warn!("policy from {url} not applied ({error}); using local config"),
+        }
+    }
 
-// ============================================================================
-// Main Entry Point
-// ============================================================================
+    // A release build only trusts a config IT vouched for to allow safe
+    // mode; otherwise `--config` with `disallow_safe_mode: false` would be
+    // all it takes to start unlocked
+    let safe_mode_vetted = cfg!(debug_assertions) || config_vetted();
+    let safe_mode = cli.safe_mode && !config.security.disallow_safe_mode && safe_mode_vetted;
+    if safe_mode {
+        log::warn!(
+            "SAFE MODE: no keyboard hook, process, monitor or capture enforcement this session"
+        );
+    } else if cli.safe_mode && config.security.disallow_safe_mode {
+        log::error!("--safe-mode refused by security.disallow_safe_mode; starting locked down");
+    } else if cli.safe_mode {
+        log::error!(
+            "--safe-mode refused: the config isn't embedded or pinned by LOCKDOWN_CONFIG_SHA256; starting locked down"
+        );
+    }
 
-fn main() {
-    let config = LockdownConfig::load();
+    #[cfg(target_os = "windows")]
+    if let Some(message) = startup_environment_error(&config.security).filter(|_| !safe_mode) {
+        log::error!("refusing to start: {message}");
+        windows_security::show_error(&config.window.title, &message);
+        mark_clean_exit();
+        std::process::exit(1);
+    }
 
-    let base_url = if cfg!(debug_assertions) {
-        config.base_url.clone()
-    } else {
-        config.production_url.clone()
+    let environment =
+        cli.environment
+            .or(config.force_environment)
+            .unwrap_or(if cfg!(debug_assertions) {
+                Environment::Base
+            } else {
+                Environment::Production
+            });
+    let base_url = match environment {
+        Environment::Base => config.base_url.clone(),
+        Environment::Production => config.production_url.clone(),
     };
+    log::info!("loading {base_url}");
+    let navigation_allowlist = allowed_origins(&config.allowed_origins, &base_url);
+    let deep_link_routes = DeepLinkRoutes::new(&base_url, &config);
+    #[cfg(target_os = "windows")]
+    let clear_clipboard_on_focus = config.security.clear_clipboard_on_focus && !safe_mode;
+    let max_focus_losses = config.focus.max_focus_losses;
+    let startup_grace = Duration::from_secs(config.window.startup_grace_secs);
+    let refocus_on_blur = config.focus.refocus_on_blur;
+    let recovery_max_age = Duration::from_secs(config.crash_recovery.max_age_minutes * 60);
+    let exit_confirmation = config.debug_settings.exit_confirmation;
+    let hide_content_on_blur = config.focus.hide_content_on_blur;
     let loading_recovery_enabled = config.loading_recovery.enabled;
     let loading_recovery_init_script = loading_recovery_script(&config.loading_recovery);
+    let mut accessibility = config.accessibility;
+    if !FONT_SCALE_RANGE.contains(&accessibility.font_scale) {
+        log::warn!(
+            "accessibility.font_scale {} out of range; using 1.0",
+            accessibility.font_scale
+        );
+        accessibility.font_scale = 1.0;
+    }
+    let accessibility_init_script = accessibility_script(&accessibility);
     let auto_update_enabled = !cfg!(debug_assertions);
     let quiz_state = Arc::new(QuizSessionState::new(auto_update_enabled));
+    let public_config = PublicConfig::new(&config, environment, &base_url, safe_mode);
 
     tauri::Builder::default()
         .manage(Arc::clone(&quiz_state))
+        .manage(config.security.clone())
+        .manage(public_config)
+        .manage(deep_link_routes.clone())
+        .manage(LockdownStages(config.stages.clone()))
         // single-instance must be the first plugin; with the "deep-link"
         // feature it forwards deep links from second launches to this instance
+        // (they arrive through on_open_url below, so argv needs no parsing
+        // here) and the second process exits 0 without creating a window
         .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
             if let Some(win) = app.get_webview_window("main") {
                 let _ = win.set_focus();
@@ -635,72 +7955,295 @@ fn main() {
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .setup(move |app| {
+            let _ = SETUP_AT.set(Instant::now());
+
             // Register the native recovery path before enabling any lockdown
-            // behavior. If registration fails, setup aborts and the app exits
-            // without taking control of the machine.
-            let app_handle_exit = app.handle().clone();
-            let shortcut = Shortcut::new(
-                Some(Modifiers::CONTROL | Modifiers::ALT | Modifiers::SHIFT),
-                Code::KeyQ,
+            // behavior. If neither the shortcut nor its fallback registers,
+            // setup aborts and the app exits without taking control of the
+            // machine.
+            let mut shortcut_label = None;
+            for (shortcut, label) in config.emergency_exit.candidates() {
+                let app_handle_exit = app.handle().clone();
+                let registered =
+                    app.global_shortcut()
+                        .on_shortcut(shortcut, move |_app, _shortcut, event| {
+                            if event.state == ShortcutState::Pressed {
+                                emergency_exit(&app_handle_exit, exit_confirmation);
+                            }
+                        });
+                match registered {
+                    Ok(()) => {
+                        shortcut_label = Some(label);
+                        break;
+                    }
+                    Err(error) => log::error!(
+                        "failed to register recovery shortcut {label} \
+                         (another app may already use it): {error}"
+                    ),
+                }
+            }
+            let Some(shortcut_label) = shortcut_label else {
+                return Err("no recovery shortcut could be registered".into());
+            };
+            log::info!("recovery shortcut ready: {shortcut_label}");
+            #[cfg(debug_assertions)]
+            eprintln!(
+                "\n==============================================\n  \
+                 Emergency exit: {shortcut_label}\n\
+                 ==============================================\n"
             );
-            app.global_shortcut()
-                .on_shortcut(shortcut, move |_app, _shortcut, event| {
-                    if event.state == ShortcutState::Pressed {
-                        println!("[Lockdown] emergency exit triggered");
-                        app_handle_exit.exit(0);
+
+            match app.path().app_data_dir() {
+                Ok(dir) => match ViolationLog::open(dir.join("violations.jsonl")) {
+                    Ok(log) => {
+                        log::info!("violation log: {}", log.path.display());
+                        let _ = VIOLATION_LOG.set(log);
                     }
-                })?;
-            println!("[Lockdown] recovery shortcut ready: Ctrl+Alt+Shift+Q");
+                    Err(error) => log::error!("failed to open violation log: {error}"),
+                },
+                Err(error) => log::warn!("no app data dir for violation log: {error}"),
+            }
+            if safe_mode {
+                log_event("safe-mode", &serde_json::json!({}));
+            }
 
             let dl = app.deep_link();
 
-            // Register the URL scheme at runtime so deep links work in dev
-            // builds and portable installs (installers also register it)
+            // Register the URL schemes at runtime so deep links work in dev
+            // builds and portable installs (installers also register the
+            // tauri.conf.json ones). macOS only knows the bundle's Info.plist
+            // schemes, so extra ones have to be added there.
             #[cfg(any(target_os = "windows", target_os = "linux"))]
-            let _ = dl.register_all();
+            {
+                let _ = dl.register_all();
+                for scheme in &deep_link_routes.schemes {
+                    if let Err(error) = dl.register(scheme) {
+                        log::warn!("failed to register deep link scheme {scheme:?}: {error}");
+                    }
+                }
+            }
 
-            let entry = dl
+            // A deep link is a deliberate new launch and wins over resuming,
+            // unless this is `restart_clean` relaunching with the arguments
+            // of the launch it ended
+            let restarted_clean = take_restart_clean_marker();
+            if restarted_clean {
+                log::info!("restarted clean; starting on the base URL");
+            }
+            let deep_link = dl
                 .get_current()
                 .ok()
-                .and_then(|opt| opt.and_then(|v| v.into_iter().next()))
-                .and_then(|u| Url::parse(&to_local(&u, &base_url)).ok())
-                .map(WebviewUrl::External)
-                .unwrap_or_else(|| WebviewUrl::External(Url::parse(&base_url).unwrap()));
+                .flatten()
+                .filter(|links| !restarted_clean && !links.is_empty())
+                .map(|links| deep_link_target(app.handle(), &links, &deep_link_routes));
+            let recovered = app.path().app_data_dir().ok().and_then(|dir| {
+                load_recovery_marker(&dir, recovery_max_age, &navigation_allowlist)
+            });
+            let recovered = match recovered {
+                Some((url, session)) if deep_link.is_none() => {
+                    log::warn!("resuming crashed session {session} at {url}");
+                    log_event(
+                        "session-resumed",
+                        &serde_json::json!({ "session_id": session, "url": url.as_str() }),
+                    );
+                    let _ = SESSION_ID.set(session);
+                    Some(url)
+                }
+                _ => None,
+            };
+            let entry = deep_link
+                .or(recovered)
+                .unwrap_or_else(|| Url::parse(&base_url).unwrap());
+            *RETRY_URL.lock().unwrap() = Some(entry.clone());
+            if !recovery_max_age.is_zero() {
+                let recovery_handle = app.handle().clone();
+                let recovery_state = Arc::clone(&quiz_state);
+                let allowed = navigation_allowlist.clone();
+                thread::spawn(move || {
+                    keep_recovery_marker(recovery_handle, recovery_state, allowed)
+                });
+            }
+            let entry = WebviewUrl::External(entry);
+
+            let (init_script, init_script_info) = load_init_script(app.handle());
+            log::info!(
+                "init script v{} ({}, sha256 {})",
+                init_script_info.version,
+                init_script_info.source,
+                init_script_info.sha256
+            );
+            let _ = INIT_SCRIPT_INFO.set(init_script_info);
 
+            // Place every window on the primary monitor explicitly; otherwise
+            // fullscreen lands on whichever monitor has the cursor
             let primary_monitor = app.primary_monitor().ok().flatten();
-            let (screen_w, screen_h) = if let Some(ref monitor) = primary_monitor {
-                let size = monitor.size();
-                let scale = monitor.scale_factor();
-                (size.width as f64 / scale, size.height as f64 / scale)
-            } else {
-                (1920.0, 1080.0)
-            };
+            let (screen_x, screen_y, screen_w, screen_h) =
+                if let Some(ref monitor) = primary_monitor {
+                    let position = monitor.position();
+                    let size = monitor.size();
+                    let scale = monitor.scale_factor();
+                    (
+                        position.x as f64 / scale,
+                        position.y as f64 / scale,
+                        size.width as f64 / scale,
+                        size.height as f64 / scale,
+                    )
+                } else {
+                    (0.0, 0.0, 1920.0, 1080.0)
+                };
 
             let sidebar_w = 320.0f64.min(screen_w * 0.2);
             let main_w = screen_w - sidebar_w;
 
+            // The keyboard hook goes in before any window exists, so the first
+            // Alt+Tab after launch can't beat it. If setup fails from here on,
+            // the hook is removed with the process.
+            #[cfg(target_os = "windows")]
+            if !safe_mode {
+                if let Some(blocked_keys) = windows_security::subscribe_blocked_keys() {
+                    let app_handle = app.handle().clone();
+                    thread::spawn(move || forward_blocked_keys(app_handle, blocked_keys));
+                }
+                if !windows_security::install_keyboard_hook(&config.keyboard) {
+                    log::error!("running without the keyboard hook; preflight will report it");
+                } else if windows_security::run_self_test() {
+                    log::info!("keyboard hook self-test passed");
+                    // Only watched once it has worked: a hook policy blocks
+                    // outright would be reinstalled forever
+                    let interval = config.keyboard.hook_watchdog_secs;
+                    if interval > 0 {
+                        let app_handle = app.handle().clone();
+                        let interval = Duration::from_secs(interval);
+                        thread::spawn(move || watch_keyboard_hook(app_handle, interval));
+                    }
+                }
+            }
+
+            let relaxed = config.window.relaxed_options();
+            if !relaxed.is_empty() {
+                log::warn!("main window relaxed by config: {}", relaxed.join(", "));
+            }
+            let request_allowlist = navigation_allowlist.clone();
+            let page_load_state = Arc::clone(&quiz_state);
             let mut main_builder = WebviewWindowBuilder::new(app, "main", entry)
-                .initialization_script(INIT_SCRIPT)
+                .initialization_script(&init_script)
+                .initialization_script(&accessibility_init_script)
+                // INIT_SCRIPT only intercepts the shortcuts; this turns the
+                // inspector itself off (including WebView2's "Inspect" context
+                // menu entry) so release builds can't open it even with the
+                // page script stripped. Cargo.toml must never enable tauri's
+                // `devtools` feature, which would allow it in release.
+                .devtools(cfg!(debug_assertions))
                 .title(&config.window.title)
                 .always_on_top(config.window.always_on_top)
                 .skip_taskbar(config.window.skip_taskbar)
-                .decorations(false)
-                .resizable(false)
-                .maximizable(false)
-                .minimizable(false)
-                .closable(false);
+                .decorations(config.window.decorations)
+                .resizable(config.window.resizable)
+                .maximizable(config.window.maximizable)
+                .minimizable(config.window.minimizable)
+                .closable(config.window.closable)
+                // Cancel in-page links and redirects that would leave the
+                // approved origins
+                .on_navigation(move |url| {
+                    let allowed =
+                        navigation_allowed(url, &navigation_allowlist) || is_offline_page(url);
+                    if !allowed {
+                        log::warn!("navigation blocked: {url}");
+                    }
+                    allowed
+                })
+                // Only before the quiz is ready: afterwards a false negative
+                // would throw away a live attempt
+                .on_page_load(move |window, payload| {
+                    let url = payload.url();
+                    if payload.event() == PageLoadEvent::Finished {
+                        apply_watermark(&window);
+                    }
+                    if payload.event() == PageLoadEvent::Finished
+                        && matches!(url.scheme(), "http" | "https")
+                        && !page_load_state.is_ready()
+                    {
+                        let app = window.app_handle().clone();
+                        let url = url.clone();
+                        thread::spawn(move || check_page_load(app, url));
+                    }
+                });
+            // Tauri's own drop handler swallows OS drops before the webview
+            // sees them (reporting them as the DragDrop window event below);
+            // without it the page gets ordinary HTML5 drop events
+            if !config.window.block_file_drop {
+                main_builder = main_builder.disable_drag_drop_handler();
+            }
+            if config.network_allowlist.enabled {
+                let origins = websocket_origins(&config.network_allowlist.websocket_origins);
+                main_builder = main_builder.initialization_script(websocket_guard_script(&origins));
+            }
+            if safe_mode {
+                main_builder = main_builder.initialization_script(safe_mode_script());
+            }
+            if ALARM.get().is_some() {
+                main_builder = main_builder.initialization_script(alarm_script());
+            }
+            if config.watermark.enabled {
+                let _ = WATERMARK_TEMPLATE.set(config.watermark.template.clone());
+                main_builder =
+                    main_builder.initialization_script(watermark_script(config.watermark.opacity));
+            }
+            // WKWebView has no per-webview data directory; macOS only gets
+            // the wipe at exit
+            if config.security.isolate_browser_data {
+                CLEAR_BROWSER_DATA_ON_EXIT.store(true, Ordering::SeqCst);
+                #[cfg(not(target_os = "macos"))]
+                if let Some(dir) = session_browser_data_dir(app.handle()) {
+                    log::info!("webview profile: {}", dir.display());
+                    main_builder = main_builder.data_directory(dir);
+                }
+            }
 
             if loading_recovery_enabled {
                 main_builder = main_builder
                     .fullscreen(false)
-                    .position(sidebar_w, 0.0)
+                    .position(screen_x + sidebar_w, screen_y)
                     .inner_size(main_w, screen_h);
             } else {
-                main_builder = main_builder.fullscreen(config.window.fullscreen);
+                main_builder = main_builder.position(screen_x, screen_y);
+                main_builder = match config.window.mode {
+                    WindowMode::Fullscreen => main_builder.fullscreen(config.window.fullscreen),
+                    // Still undecorated, non-resizable and non-closable, like
+                    // fullscreen
+                    WindowMode::BorderlessMaximized => {
+                        main_builder.fullscreen(false).maximized(true)
+                    }
+                };
             }
 
             let _window = main_builder.build()?;
 
+            if config.network_allowlist.enabled {
+                #[cfg(target_os = "windows")]
+                {
+                    let app_handle = app.handle().clone();
+                    let filtered = windows_security::filter_web_requests(&_window, move |url| {
+                        let allowed = request_allowed(url, &request_allowlist);
+                        if !allowed {
+                            report_blocked_request(&app_handle, url);
+                        }
+                        allowed
+                    });
+                    if let Err(error) = filtered {
+                        log::error!("failed to filter web requests: {error}");
+                    }
+                }
+                #[cfg(not(target_os = "windows"))]
+                {
+                    let _ = request_allowlist;
+                    log::warn!(
+                        "network_allowlist only guards WebSockets on this platform; \
+                         other requests aren't filtered"
+                    );
+                }
+            }
+
             if loading_recovery_enabled {
                 let recovery_window = WebviewWindowBuilder::new(
                     app,
@@ -708,8 +8251,9 @@ fn main() {
                     WebviewUrl::App("loading.html".into()),
                 )
                 .initialization_script(&loading_recovery_init_script)
+                .devtools(cfg!(debug_assertions))
                 .title("Quiz loading")
-                .position(0.0, 0.0)
+                .position(screen_x, screen_y)
                 .inner_size(sidebar_w, screen_h)
                 .always_on_top(true)
                 .skip_taskbar(true)
@@ -728,17 +8272,107 @@ fn main() {
                 }
             }
 
-            // Exclude the window from screenshots/screen recordings on macOS
-            #[cfg(target_os = "macos")]
-            macos_security::disable_window_capture(&_window);
+            if config.window.cover_secondary_monitors && !safe_mode {
+                let primary_position = primary_monitor.as_ref().map(|monitor| *monitor.position());
+                let secondary = app
+                    .available_monitors()?
+                    .into_iter()
+                    .filter(|monitor| Some(*monitor.position()) != primary_position);
+                for (index, monitor) in secondary.enumerate() {
+                    let scale = monitor.scale_factor();
+                    let position = monitor.position().to_logical::<f64>(scale);
+                    let size = monitor.size().to_logical::<f64>(scale);
+                    WebviewWindowBuilder::new(
+                        app,
+                        format!("{COVER_WINDOW_PREFIX}{index}"),
+                        WebviewUrl::App("cover.html".into()),
+                    )
+                    .devtools(cfg!(debug_assertions))
+                    .title(&config.window.title)
+                    .position(position.x, position.y)
+                    .inner_size(size.width, size.height)
+                    .fullscreen(true)
+                    .always_on_top(true)
+                    .skip_taskbar(true)
+                    .decorations(false)
+                    .resizable(false)
+                    .maximizable(false)
+                    .minimizable(false)
+                    .closable(false)
+                    .focused(false)
+                    .build()?;
+                }
+            }
+
+            // The emergency exit above stays registered; this is the native
+            // enforcement that safe mode leaves out
+            if !safe_mode {
+                // Exclude the window from screenshots/screen recordings on macOS
+                #[cfg(target_os = "macos")]
+                macos_security::disable_window_capture(&_window);
 
-            // Activate platform lockdown only after both the recovery shortcut
-            // and browser window have initialized successfully.
-            #[cfg(target_os = "windows")]
-            windows_security::install_keyboard_hook();
+                #[cfg(target_os = "windows")]
+                if config.security.exclude_from_capture {
+                    if let Err(error) = windows_security::set_capture_excluded(&_window, true) {
+                        log::error!("failed to exclude window from capture: {error}");
+                    }
+                }
+
+                // Activate the rest of the platform lockdown only after both the
+                // recovery shortcut and browser window have initialized successfully.
+                #[cfg(target_os = "windows")]
+                {
+                    start_process_watcher(app.handle().clone(), config.security.clone());
+                    let idle_handle = app.handle().clone();
+                    let idle = config.idle;
+                    thread::spawn(move || watch_idle(idle_handle, idle));
+                    let idle_enabled = idle.warning_minutes > 0 || idle.timeout_minutes > 0;
+                    if idle_enabled && idle.detect_synthetic_input {
+                        let synthetic_handle = app.handle().clone();
+                        thread::spawn(move || watch_synthetic_input(synthetic_handle));
+                    }
+                    if config.window.always_on_top && config.window.reassert_topmost_ms > 0 {
+                        let topmost_handle = app.handle().clone();
+                        let interval = Duration::from_millis(config.window.reassert_topmost_ms);
+                        thread::spawn(move || keep_topmost(topmost_handle, interval));
+                    }
+                    if config.window.suppress_touch_keyboard {
+                        windows_security::suppress_touch_keyboard();
+                        let touch_handle = app.handle().clone();
+                        thread::spawn(move || watch_touch_keyboard(touch_handle));
+                    }
+                    let interval = config.security.clear_clipboard_interval_secs;
+                    if interval > 0 {
+                        let interval = Duration::from_secs(interval);
+                        thread::spawn(move || clear_clipboard_periodically(interval));
+                    }
+                }
+
+                #[cfg(target_os = "linux")]
+                linux_security::install_keyboard_hook();
+
+                #[cfg(target_os = "macos")]
+                {
+                    macos_security::enable_kiosk_mode();
+                    macos_security::install_keyboard_hook();
+                }
+
+                let monitor_handle = app.handle().clone();
+                thread::spawn(move || watch_monitors(monitor_handle));
+
+                #[cfg(target_os = "windows")]
+                {
+                    let devices_handle = app.handle().clone();
+                    thread::spawn(move || watch_input_devices(devices_handle));
+                }
+            }
 
-            #[cfg(target_os = "macos")]
-            macos_security::enable_kiosk_mode();
+            #[cfg(target_os = "windows")]
+            if config.power.low_battery_percent > 0 {
+                let power_handle = app.handle().clone();
+                let threshold = config.power.low_battery_percent;
+                thread::spawn(move || watch_power(power_handle, threshold));
+            }
 
             // Release builds check and install only while the app owns the
             // pre-quiz loading phase. Debug builds never replace themselves.
@@ -747,46 +8381,161 @@ fn main() {
                 let updater_state = Arc::clone(&quiz_state);
                 tauri::async_runtime::spawn(async move {
                     if let Err(e) = check_for_updates(updater_handle, updater_state).await {
-                        eprintln!("[Lockdown] update check failed: {e}");
+                        log::error!("update check failed: {e}");
                     }
                 });
             }
 
             // Handle deep-links for the already-running instance
             let app_handle: AppHandle = app.handle().clone();
-            let base_clone = base_url.clone();
             dl.on_open_url(move |evt: OpenUrlEvent| {
-                if let Some(u) = evt.urls().first() {
-                    if let Some(win) = app_handle.get_webview_window("main") {
-                        // navigate() instead of eval() so a crafted deep link
-                        // cannot inject script into the page
-                        if let Ok(target) = Url::parse(&to_local(u, &base_clone)) {
-                            let _ = win.navigate(target);
-                        }
-                        let _ = win.set_focus();
-                    }
+                let links = evt.urls();
+                if links.is_empty() {
+                    return;
+                }
+                if let Some(win) = app_handle.get_webview_window("main") {
+                    // navigate() instead of eval() so a crafted deep link
+                    // cannot inject script into the page
+                    let _ = win.navigate(deep_link_target(&app_handle, &links, &deep_link_routes));
+                    let _ = win.set_focus();
                 }
             });
 
+            STARTUP_COMPLETE.store(true, Ordering::SeqCst);
             Ok(())
         })
-        .on_window_event(|window, event| {
-            if window.label() == "main" {
-                if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                    api.prevent_close();
+        .on_window_event(move |window, event| {
+            if is_cover_window(window.label()) || window.label() == PROCTOR_BANNER_LABEL {
+                match event {
+                    tauri::WindowEvent::CloseRequested { api, .. } => api.prevent_close(),
+                    // A click on a cover window or the banner hands focus
+                    // straight back
+                    tauri::WindowEvent::Focused(true) => {
+                        if let Some(main) = window.app_handle().get_webview_window("main") {
+                            let _ = main.set_focus();
+                        }
+                    }
+                    _ => {}
+                }
+                return;
+            }
+            if window.label() != "main" {
+                return;
+            }
+            match event {
+                tauri::WindowEvent::CloseRequested { api, .. } => {
+                    if CLOSE_ALLOWED.swap(false, Ordering::SeqCst) {
+                        return;
+                    }
+                    let app = window.app_handle();
+                    let in_grace = SETUP_AT
+                        .get()
+                        .is_some_and(|at| at.elapsed() < startup_grace);
+                    if in_grace && !app.state::<Arc<QuizSessionState>>().is_ready() {
+                        exit_lockdown(app, "closed-during-startup");
+                    } else {
+                        api.prevent_close();
+                    }
+                }
+                tauri::WindowEvent::Focused(true) => {
+                    focus_regained();
+                    if hide_content_on_blur {
+                        if let Some(main) = window.app_handle().get_webview_window("main") {
+                            set_content_hidden(&main, false);
+                        }
+                    }
+                    #[cfg(target_os = "windows")]
+                    if clear_clipboard_on_focus {
+                        windows_security::clear_clipboard();
+                    }
+                }
+                tauri::WindowEvent::Focused(false) => {
+                    if hide_content_on_blur {
+                        if let Some(main) = window.app_handle().get_webview_window("main") {
+                            set_content_hidden(&main, true);
+                        }
+                    }
+                    let app = window.app_handle().clone();
+                    thread::spawn(move || {
+                        track_focus_loss(
+                            app,
+                            max_focus_losses,
+                            refocus_on_blur,
+                            hide_content_on_blur,
+                        )
+                    });
+                }
+                // Only delivered while `window.block_file_drop` keeps Tauri's
+                // drop handler, which has already kept the files from the page
+                tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) => {
+                    log::warn!("blocked {} file(s) dropped on the quiz", paths.len());
+                    emit_violation(
+                        window.app_handle(),
+                        "file-drop-blocked",
+                        FileDropPayload { count: paths.len() },
+                    );
                 }
+                _ => {}
             }
         })
         .invoke_handler(tauri::generate_handler![
+            submit_and_exit,
+            attempt_exit,
+            restart_clean,
+            list_forbidden_processes,
+            terminate_process,
+            arm_hard_deadline,
+            cancel_deadline,
+            start_timer,
+            stop_timer,
+            show_proctor_banner,
+            get_always_on_top,
+            set_always_on_top,
             close_app,
             close_lockdown,
             close_during_loading,
+            confirm_emergency_exit,
+            cancel_emergency_exit,
+            reload_quiz,
+            save_view_state,
+            restore_view_state,
             mark_quiz_ready,
+            set_exam_active,
+            check_for_update,
+            apply_update,
+            set_keyboard_lockdown,
+            set_paste_allowed,
+            set_accessibility,
+            set_lockdown_stage,
+            set_watermark_text,
+            attest,
+            get_script_version,
+            get_config_hash,
+            get_config,
+            resolve_deeplink,
+            export_violation_log,
+            upload_violations,
+            get_recent_violations,
+            get_session_summary,
+            clear_browser_data,
+            get_foreground_window_title,
+            capture_checkpoint,
             check_multiple_monitors,
-            get_monitor_count
+            get_monitor_count,
+            get_monitor_counts,
+            set_fullscreen_on_monitor,
+            get_power_status,
+            get_displays,
+            get_security_status,
+            run_preflight,
+            verify_self_integrity,
+            rescan_environment,
+            set_capture_exclusion
         ])
         .build(tauri::generate_context!())
-        .expect("error while building lockdown browser")
+        .unwrap_or_else(|error| {
+            startup_failed(&message("startup-failed", &[("error", &error.to_string())]))
+        })
         .run(|_app, event| {
             if let RunEvent::ExitRequested { code, api, .. } = event {
                 // Block Cmd+Q and other OS-initiated quits; explicit exits
@@ -798,3 +8547,325 @@ fn main() {
             }
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyboard_config_parses_blocked_combos() {
+        let keyboard: KeyboardConfig = serde_json::from_str(
+            r#"{
+                "blocked_combos": [
+                    { "modifiers": ["alt"], "key": "Tab" },
+                    { "modifiers": ["ctrl", "shift"], "key": "Escape", "action": "warn" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(keyboard.blocked_combos.len(), 2);
+        let alt_tab = &keyboard.blocked_combos[0];
+        assert_eq!(alt_tab.key, "Tab");
+        assert!(alt_tab.modifiers == [KeyModifier::Alt]);
+        assert!(alt_tab.action == KeyAction::Block);
+        let task_manager = &keyboard.blocked_combos[1];
+        assert_eq!(task_manager.key, "Escape");
+        assert!(task_manager.modifiers == [KeyModifier::Ctrl, KeyModifier::Shift]);
+        assert!(task_manager.action == KeyAction::Warn);
+        // Fields left out keep their defaults
+        assert_eq!(keyboard.hook_watchdog_secs, 5);
+        assert!(keyboard.block_accessibility_shortcuts);
+    }
+
+    const BASE: &str = "https://exam.example.com";
+
+    fn local(link: &str, base: &str) -> Result<String, String> {
+        let link = Url::parse(link).map_err(|error| error.to_string())?;
+        to_local(&link, base, &[DEEP_LINK_SCHEME.to_owned()]).map(String::from)
+    }
+
+    #[test]
+    fn to_local_routes_a_quiz_link() {
+        assert_eq!(
+            local("understandly-lockdown://quiz?x=1", BASE).unwrap(),
+            "https://exam.example.com/quiz?x=1"
+        );
+        // An underscore isn't valid in a URL scheme, so this spelling never
+        // even parses as a link
+        assert!(Url::parse("understandly_lockdown://quiz?x=1").is_err());
+    }
+
+    #[test]
+    fn to_local_rejects_other_schemes() {
+        assert!(local("https://evil.example/quiz", BASE).is_err());
+        assert!(local("understandly-lockdown.evil.example://quiz", BASE).is_err());
+        assert!(local("javascript:alert(1)", BASE).is_err());
+    }
+
+    #[test]
+    fn to_local_keeps_protocol_relative_hosts_on_the_base() {
+        // Extra slashes only produce an (empty) host; the rest stays a path
+        // on the base origin
+        assert_eq!(
+            local("understandly-lockdown:////evil.example/quiz", BASE).unwrap(),
+            "https://exam.example.com/evil.example/quiz"
+        );
+        // Encoded slashes would decode to //evil.example
+        assert!(local("understandly-lockdown://%2F%2Fevil.example/quiz", BASE).is_err());
+        assert!(local("understandly-lockdown://quiz/%2F%2Fevil.example", BASE).is_err());
+    }
+
+    #[test]
+    fn to_local_encodes_quotes_in_the_query() {
+        // Deep links reach the webview through `navigate`, never a script,
+        // and the quote can't close a string literal even if one were built
+        let target = local("understandly-lockdown://quiz?c=a'+alert(1)+'b", BASE).unwrap();
+        assert_eq!(target, "https://exam.example.com/quiz?c=a%27+alert(1)+%27b");
+        assert!(!target.contains('\''));
+    }
+
+    #[test]
+    fn navigation_stays_on_allowed_origins() {
+        let allowed = allowed_origins(&[], BASE);
+        let allowed_url = |url: &str| navigation_allowed(&Url::parse(url).unwrap(), &allowed);
+        assert!(allowed_url("https://exam.example.com/quiz"));
+        assert!(!allowed_url("https://evil.example/"));
+        assert!(!allowed_url("https://exam.example.com.evil.example/quiz"));
+        assert!(!allowed_url("http://exam.example.com/quiz"));
+    }
+
+    /// Only the two required fields; everything else takes its default
+    const MINIMAL_CONFIG: &str = r#"{"base_url":"http://x","production_url":"http://y"}"#;
+
+    #[test]
+    fn minimal_config_takes_window_defaults() {
+        let config: LockdownConfig = serde_json::from_str(MINIMAL_CONFIG).unwrap();
+        assert_eq!(config.base_url, "http://x");
+        assert_eq!(config.production_url, "http://y");
+
+        let window = &config.window;
+        assert_eq!(window.title, "Understandly Lockdown");
+        assert!(matches!(window.mode, WindowMode::Fullscreen));
+        assert!(window.fullscreen);
+        assert!(window.always_on_top);
+        assert_eq!(window.always_on_top_restore_secs, 120);
+        assert!(window.skip_taskbar);
+        assert!(window.block_file_drop);
+        assert!(!window.cover_secondary_monitors);
+        assert_eq!(window.startup_grace_secs, 0);
+        assert!(window.relaxed_options().is_empty());
+    }
+
+    #[test]
+    fn preflight_report_serializes_every_failure() {
+        let report = PreflightReport {
+            ok: false,
+            failures: vec![
+                PreflightFailure {
+                    code: "multiple-monitors",
+                    message: "Disconnect your second monitor".into(),
+                },
+                PreflightFailure {
+                    code: "remote-session",
+                    message: "Close your remote desktop session".into(),
+                },
+            ],
+            input_devices: None,
+            self_integrity: true,
+        };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "ok": false,
+                "failures": [
+                    { "code": "multiple-monitors", "message": "Disconnect your second monitor" },
+                    { "code": "remote-session", "message": "Close your remote desktop session" }
+                ],
+                "input_devices": null,
+                "self_integrity": true
+            })
+        );
+    }
+
+    #[test]
+    fn recent_violations_returns_the_latest() {
+        for kind in ["focus-lost", "blocked-key", "forbidden-process"] {
+            remember_violation(Violation {
+                kind: kind.into(),
+                severity: Severity::of(kind),
+                details: serde_json::json!({}),
+                timestamp_ms: unix_time_ms(),
+            });
+        }
+        let kinds: Vec<String> = get_recent_violations(2)
+            .into_iter()
+            .map(|violation| violation.kind)
+            .collect();
+        assert_eq!(kinds, ["blocked-key", "forbidden-process"]);
+    }
+
+    #[test]
+    fn deep_link_paths_follow_the_allowlist() {
+        let path = |link: &str| deep_link_path(&Url::parse(link).unwrap());
+        let allowed = ["quiz".to_owned(), "results/*".to_owned()];
+        let allowed_link = |link| deep_link_path_allowed(&path(link), &allowed);
+
+        assert!(allowed_link("understandly-lockdown://quiz?x=1"));
+        assert!(allowed_link("understandly-lockdown://results/987"));
+        assert!(!allowed_link("understandly-lockdown://admin"));
+        assert!(!allowed_link("understandly-lockdown://quiz/admin"));
+        // No list allows every path
+        assert!(deep_link_path_allowed("admin", &[]));
+    }
+
+    #[test]
+    fn resolve_deeplink_previews_the_target() {
+        let config: LockdownConfig = serde_json::from_value(serde_json::json!({
+            "base_url": BASE,
+            "production_url": BASE,
+            "allowed_deeplink_paths": ["quiz"]
+        }))
+        .unwrap();
+        let routes = DeepLinkRoutes::new(BASE, &config);
+        let resolve = |link: &str| routes.resolve(&Url::parse(link).unwrap()).map(String::from);
+
+        assert_eq!(
+            resolve("understandly-lockdown://quiz?x=1").unwrap(),
+            "https://exam.example.com/quiz?x=1"
+        );
+        assert!(resolve("other-app://quiz?x=1")
+            .unwrap_err()
+            .contains("unexpected scheme"));
+        assert!(resolve("understandly-lockdown://admin")
+            .unwrap_err()
+            .contains("not allowed"));
+    }
+
+    #[test]
+    fn config_parses_lockdown_stages() {
+        let config: LockdownConfig = serde_json::from_value(serde_json::json!({
+            "base_url": BASE,
+            "production_url": BASE,
+            "stages": {
+                "instructions": {
+                    "keyboard_lockdown": false,
+                    "paste_allowed": true,
+                    "monitor_enforcement": false
+                },
+                "exam": {}
+            }
+        }))
+        .unwrap();
+
+        let names: Vec<&str> = config.stages.keys().map(String::as_str).collect();
+        assert_eq!(names, ["exam", "instructions"]);
+
+        let instructions = &config.stages["instructions"];
+        assert!(!instructions.keyboard_lockdown);
+        assert!(instructions.paste_allowed);
+        assert!(instructions.always_on_top);
+        assert!(!instructions.monitor_enforcement);
+        assert!(instructions.relaxes_protection());
+
+        let exam = &config.stages["exam"];
+        assert!(exam.keyboard_lockdown);
+        assert!(!exam.paste_allowed);
+        assert!(exam.always_on_top);
+        assert!(exam.monitor_enforcement);
+        assert!(!exam.relaxes_protection());
+    }
+
+    #[test]
+    fn window_config_relaxes_only_what_is_asked() {
+        let config: LockdownConfig = serde_json::from_value(serde_json::json!({
+            "base_url": BASE,
+            "production_url": BASE,
+            "window": { "resizable": true }
+        }))
+        .unwrap();
+
+        // The main window builder takes each of these straight from the config
+        let window = &config.window;
+        assert!(window.resizable);
+        assert!(!window.decorations);
+        assert!(!window.maximizable);
+        assert!(!window.minimizable);
+        assert!(!window.closable);
+        assert_eq!(window.relaxed_options(), ["resizable"]);
+    }
+
+    /// A base URL with a path, as staging deployments use
+    const APP_BASE: &str = "https://host.example/exam-app";
+
+    #[test]
+    fn to_local_joins_bases_with_paths_and_queries() {
+        let with_query = format!("{APP_BASE}?tenant=7");
+        let on_app = |link| local(link, APP_BASE).unwrap();
+        let on_query = |link| local(link, &with_query).unwrap();
+
+        assert_eq!(
+            on_app("understandly-lockdown://quiz?x=1"),
+            "https://host.example/exam-app/quiz?x=1"
+        );
+        assert_eq!(
+            on_app("understandly-lockdown://"),
+            "https://host.example/exam-app"
+        );
+        assert_eq!(
+            local("understandly-lockdown://quiz", &format!("{APP_BASE}/")).unwrap(),
+            "https://host.example/exam-app/quiz"
+        );
+        // The base's query comes first
+        assert_eq!(
+            on_query("understandly-lockdown://quiz?x=1"),
+            "https://host.example/exam-app/quiz?tenant=7&x=1"
+        );
+        assert_eq!(
+            on_query("understandly-lockdown://results/987"),
+            "https://host.example/exam-app/results/987?tenant=7"
+        );
+    }
+
+    #[test]
+    fn to_local_never_doubles_or_drops_slashes() {
+        let on_app = |link| local(link, APP_BASE).unwrap();
+        assert_eq!(
+            on_app("understandly-lockdown:quiz"),
+            "https://host.example/exam-app/quiz"
+        );
+        assert_eq!(
+            on_app("understandly-lockdown:////quiz/"),
+            "https://host.example/exam-app/quiz"
+        );
+        assert_eq!(
+            on_app("understandly-lockdown:///quiz#q3"),
+            "https://host.example/exam-app/quiz#q3"
+        );
+        assert_eq!(
+            on_app("understandly-lockdown://results//987/"),
+            "https://host.example/exam-app/results/987"
+        );
+        assert_eq!(
+            local("understandly-lockdown:///quiz#q3", BASE).unwrap(),
+            "https://exam.example.com/quiz#q3"
+        );
+    }
+
+    #[test]
+    fn public_config_never_exposes_the_exit_password_hash() {
+        let hash = "pbkdf2-sha256$600000$c0ffee-salt$8d969eef6ecad3c29a3a629280e686cf";
+        let config: LockdownConfig = serde_json::from_str(&format!(
+            r#"{{"base_url":"http://x","production_url":"http://y",
+                "security":{{"exit_password_hash":"{hash}"}}}}"#
+        ))
+        .unwrap();
+        let public = PublicConfig::new(&config, Environment::Base, "http://x", false);
+        let json = serde_json::to_string(&public).unwrap();
+        assert!(!json.contains(hash), "{json}");
+        assert!(!json.contains("8d969eef"), "{json}");
+        assert!(!json.contains("exit_password_hash"), "{json}");
+        assert!(json.contains(r#""exit_password_set":true"#), "{json}");
+        assert!(json.contains(r#""security":{"#), "{json}");
+    }
+}